@@ -1,4 +1,4 @@
-#![allow(clippy::eval_order_dependence)]
+#![allow(clippy::mixed_read_write_in_expression)]
 
 use std::path::PathBuf;
 
@@ -22,18 +22,23 @@ impl Assets {
                     .to_string_lossy()
                     .as_ref(),
             )
-            .await,
+            .await
+            .unwrap(),
         }
     }
 }
 
 pub struct Textures {
     pub truck: Texture2D,
+    pub van: Texture2D,
+    pub lorry: Texture2D,
+    pub fridge: Texture2D,
     pub treads: Texture2D,
 
     pub hex: Texture2D,
     pub factory: Texture2D,
     pub market: Texture2D,
+    pub warehouse: Texture2D,
     pub wreckage: Texture2D,
 
     pub instr_direct: Texture2D,
@@ -42,6 +47,10 @@ pub struct Textures {
     pub instr_rotate1: Texture2D,
     pub instr_rotate2: Texture2D,
     pub instr_rotate3: Texture2D,
+    /// Middle of a hex for instructions with more than one way out
+    pub instr_hub: Texture2D,
+    /// An arrow from the middle of a hex out to one side, for instructions that point more than one way
+    pub instr_arrow_half: Texture2D,
 
     pub card: Texture2D,
     pub delete_card: Texture2D,
@@ -59,11 +68,15 @@ impl Textures {
     pub async fn init() -> Self {
         Self {
             truck: texture("truck").await,
+            van: texture("van").await,
+            lorry: texture("lorry").await,
+            fridge: texture("fridge").await,
             treads: texture("treads").await,
 
             hex: texture("hex").await,
             factory: texture("factory").await,
             market: texture("market").await,
+            warehouse: texture("warehouse").await,
             wreckage: texture("wreckage").await,
 
             instr_direct: texture("instructions/direct").await,
@@ -72,6 +85,8 @@ impl Textures {
             instr_rotate1: texture("instructions/rotate_1").await,
             instr_rotate2: texture("instructions/rotate_2").await,
             instr_rotate3: texture("instructions/rotate_3").await,
+            instr_hub: texture("instructions/hub").await,
+            instr_arrow_half: texture("instructions/arrow_half").await,
 
             card: texture("card").await,
             delete_card: texture("card_delete").await,
@@ -1,4 +1,4 @@
-use crate::modes::playing::HAND_SIZE;
+use crate::sim::HAND_SIZE;
 use macroquad::prelude::*;
 
 use crate::Globals;
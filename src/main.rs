@@ -1,10 +1,9 @@
-#![feature(drain_filter)]
-
 mod assets;
 pub mod drawutils;
 mod modes;
+mod sim;
 use assets::Assets;
-use modes::{ModeLose, ModePlaying};
+use modes::{ModeLose, ModePlaying, ModeReplay, ModeTitle};
 
 use macroquad::prelude::*;
 
@@ -20,13 +19,21 @@ fn conf() -> Conf {
 
 #[macroquad::main(conf)]
 async fn main() {
-    let mut modes = vec![GameMode::Playing(ModePlaying::new())];
+    // Pass a seed on the command line to play a specific game
+    let seed = std::env::args().nth(1).and_then(|arg| arg.parse().ok());
+    let first_mode = match seed {
+        None if sim::save::exists() => GameMode::Title(ModeTitle::new()),
+        _ => GameMode::Playing(ModePlaying::new(seed.unwrap_or_else(::rand::random))),
+    };
+    let mut modes = vec![first_mode];
     let mut globals = Globals::new().await;
 
     loop {
         let trans = match modes.last_mut().unwrap() {
+            GameMode::Title(mode) => mode.update(&mut globals),
             GameMode::Playing(mode) => mode.update(&mut globals),
             GameMode::Lose(mode) => mode.update(&mut globals),
+            GameMode::Replay(mode) => mode.update(&mut globals),
         };
         match trans {
             Transition::Push(mode) => modes.push(mode),
@@ -36,21 +43,28 @@ async fn main() {
                 }
             }
             Transition::Swap(mode) => *modes.last_mut().unwrap() = mode,
+            Transition::Quit => break,
             Transition::None => {}
         }
 
         match modes.last().unwrap() {
+            GameMode::Title(mode) => mode.draw(&globals),
             GameMode::Playing(mode) => mode.draw(&globals),
             GameMode::Lose(mode) => mode.draw(&globals),
+            GameMode::Replay(mode) => mode.draw(&globals),
         }
 
         next_frame().await
     }
 }
 
+// There's only ever a couple of these around, so the size doesn't matter
+#[allow(clippy::large_enum_variant)]
 pub enum GameMode {
+    Title(ModeTitle),
     Playing(ModePlaying),
     Lose(ModeLose),
+    Replay(ModeReplay),
 }
 
 pub struct Globals {
@@ -74,4 +88,6 @@ pub enum Transition {
     Swap(GameMode),
     /// Pop the current state off
     Pop,
+    /// Close the game
+    Quit,
 }
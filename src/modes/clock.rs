@@ -0,0 +1,35 @@
+/// How many times the game updates per second, no matter how fast the screen is
+pub const TICKS_PER_SECOND: f32 = 60.0;
+const TICK_TIME: f32 = 1.0 / TICKS_PER_SECOND;
+/// Longest frame we'll try to catch up on.
+/// Anything slower than this (like dragging the window around) just slows the game down,
+/// instead of making it try to run a few thousand ticks at once.
+const MAX_FRAME_TIME: f32 = 0.25;
+
+/// Turns real time into a steady number of sim ticks.
+pub struct TickClock {
+    /// Time that's passed but hasn't been turned into a tick yet
+    accumulator: f32,
+}
+
+impl TickClock {
+    #[allow(clippy::new_without_default)]
+    pub fn new() -> Self {
+        Self { accumulator: 0.0 }
+    }
+
+    /// Add this frame's worth of time and return how many ticks to run.
+    ///
+    /// `speed` multiplies how fast time passes, so `4.0` runs four ticks for every one at normal speed.
+    pub fn advance(&mut self, frame_time: f32, speed: f32) -> u32 {
+        self.accumulator += frame_time.min(MAX_FRAME_TIME) * speed;
+        let ticks = (self.accumulator / TICK_TIME) as u32;
+        self.accumulator -= ticks as f32 * TICK_TIME;
+        ticks
+    }
+
+    /// How far we are from the last tick to the next one, from `0.0` to `1.0`.
+    pub fn alpha(&self) -> f32 {
+        (self.accumulator / TICK_TIME).clamp(0.0, 1.0)
+    }
+}
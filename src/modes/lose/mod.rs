@@ -1,34 +1,42 @@
 use crate::{
     drawutils::{self, TextAlign},
+    sim::replay::Recording,
     GameMode, Globals, Transition,
 };
 
-use super::ModePlaying;
+use super::{ModePlaying, ModeReplay};
 
 const TIME_HERE_TILL_RETURN: u64 = 20;
 
 pub struct ModeLose {
     /// What was your top score?
     highscore: u32,
+    /// Everything that happened in the game
+    recording: Recording,
 
     frames_elapsed: u64,
 }
 
 impl ModeLose {
-    pub fn new(highscore: u32) -> Self {
+    pub fn new(highscore: u32, recording: Recording) -> Self {
         ModeLose {
             highscore,
+            recording,
             frames_elapsed: 0,
         }
     }
 
-    pub fn update(&mut self, globals: &mut Globals) -> Transition {
+    pub fn update(&mut self, _globals: &mut Globals) -> Transition {
         use macroquad::prelude::*;
 
-        let out = if self.frames_elapsed >= TIME_HERE_TILL_RETURN
-            && is_mouse_button_pressed(MouseButton::Left)
-        {
-            Transition::Swap(GameMode::Playing(ModePlaying::new()))
+        let out = if self.frames_elapsed < TIME_HERE_TILL_RETURN {
+            Transition::None
+        } else if is_mouse_button_pressed(MouseButton::Left) {
+            Transition::Swap(GameMode::Playing(ModePlaying::new(::rand::random())))
+        } else if is_key_pressed(KeyCode::R) {
+            Transition::Swap(GameMode::Playing(ModePlaying::new(self.recording.seed)))
+        } else if is_key_pressed(KeyCode::V) {
+            Transition::Push(GameMode::Replay(ModeReplay::new(self.recording.clone())))
         } else {
             Transition::None
         };
@@ -49,6 +57,14 @@ impl ModeLose {
             200.0,
             TextAlign::Center,
         );
+        drawutils::text(
+            globals,
+            format!("Seed: {}", self.recording.seed).as_str(),
+            20,
+            400.0,
+            240.0,
+            TextAlign::Center,
+        );
         drawutils::text(
             globals,
             "Click anywhere to play again",
@@ -57,5 +73,21 @@ impl ModeLose {
             400.0,
             TextAlign::Center,
         );
+        drawutils::text(
+            globals,
+            "or press R to retry this seed",
+            20,
+            400.0,
+            430.0,
+            TextAlign::Center,
+        );
+        drawutils::text(
+            globals,
+            "Press V to watch the replay",
+            20,
+            400.0,
+            460.0,
+            TextAlign::Center,
+        );
     }
 }
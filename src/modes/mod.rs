@@ -1,4 +1,9 @@
+mod clock;
 pub(crate) mod playing;
 pub use playing::ModePlaying;
 mod lose;
 pub use lose::ModeLose;
+mod replay;
+pub use replay::ModeReplay;
+mod title;
+pub use title::ModeTitle;
@@ -1,67 +1,21 @@
-use super::{
-    cells::{Cell, Instruction},
-    economy::Item,
-};
-use crate::{drawutils, Globals};
 use crate::{
-    drawutils::{CARD_HEIGHT, CARD_WIDTH},
-    modes::playing::Truck,
+    drawutils::{self, HEX_HEIGHT, HEX_WIDTH},
+    sim::{
+        cards::{Card, CardInstruction, Dials},
+        cells::{Cell, Instruction},
+        economy::{ItemFilter, Warehouse},
+        trucks::Truck,
+        MOVE_TRUCK_FEE,
+    },
+    Globals,
 };
 
-use drawutils::{HEX_HEIGHT, HEX_WIDTH};
+use super::{cells::draw_tunnel_link, forecast::hex_center};
+
 use hex2d::{Angle, Coordinate, Direction};
 use macroquad::prelude::{draw_texture, WHITE};
-use rand::Rng;
-
-/// A card held in hand.
-#[derive(Clone)]
-pub enum Card {
-    Truck { cargo: Option<Item> },
-    Instruction(CardInstruction),
-    Cleanup,
-}
 
 impl Card {
-    /// Make the starting hand of cards
-    pub fn starting_hand() -> Vec<Self> {
-        vec![
-            Card::Truck { cargo: None },
-            Card::Truck { cargo: None },
-            Card::Instruction(CardInstruction::Direct),
-            Card::Instruction(CardInstruction::Shunt),
-            Card::Cleanup,
-        ]
-    }
-
-    /// Generate a random card.
-    pub fn generate() -> Self {
-        let mut rng = rand::thread_rng();
-
-        if rng.gen_bool(0.5) {
-            // Make an instruction
-            Card::Instruction(CardInstruction::sample())
-        } else if rng.gen_bool(0.3) {
-            Card::Cleanup
-        } else {
-            // Trucc
-            let cargo = if rng.gen_bool(0.8) {
-                None
-            } else {
-                Some(Item::sample())
-            };
-            Card::Truck { cargo }
-        }
-    }
-
-    /// Get the cost of this card
-    pub fn cost(&self) -> u32 {
-        match self {
-            Card::Truck { cargo } => 50 + if cargo.is_some() { 20 } else { 0 },
-            Card::Instruction(_) => 20,
-            Card::Cleanup => 10,
-        }
-    }
-
     /// Draw this card. `x, y` are the corner.
     pub fn draw(&self, x: f32, y: f32, current_money: u32, globals: &Globals) {
         use macroquad::prelude::*;
@@ -69,16 +23,27 @@ impl Card {
         draw_texture(globals.assets.textures.card, x, y, WHITE);
 
         let title = match self {
-            Card::Truck { .. } => "Truck",
+            Card::Truck { class, .. } => class.name(),
             Card::Instruction(instr) => match instr {
                 CardInstruction::Direct => "Direct",
                 CardInstruction::Rotate => "Rotate",
                 CardInstruction::Shunt => "Shunt",
+                CardInstruction::Sort => "Sort",
+                CardInstruction::Switch => "Switch",
+                CardInstruction::Signal => "Signal",
+                CardInstruction::Light => "Light",
+                CardInstruction::Boost => "Boost",
+                CardInstruction::Slow => "Slow",
+                CardInstruction::Tunnel => "Tunnel",
+                CardInstruction::Bridge => "Bridge",
+                CardInstruction::Dump => "Dump",
+                CardInstruction::Counter => "Counter",
             },
             Card::Cleanup => "Cleanup",
+            Card::Warehouse => "Warehouse",
         };
 
-        drawutils::center_text(globals, &title, 18, x + 49.5, y + 15.0);
+        drawutils::center_text(globals, title, 18, x + 49.5, y + 15.0);
         drawutils::center_text_color(
             globals,
             format!("${}", self.cost()).as_str(),
@@ -93,22 +58,49 @@ impl Card {
         );
 
         match self {
-            Card::Truck { cargo } => {
-                let to_draw = Truck {
-                    cargo: cargo.to_owned(),
-                    speed: 0.0,
-                    facing: Direction::XY,
-                    position: Coordinate::new(0, 0),
-                    move_progress: 0.0,
-                    out_of_bounds_immunity: false,
-                };
+            Card::Truck { cargo, class } => {
+                let to_draw = Truck::from_rot(
+                    Coordinate::new(0, 0),
+                    *class,
+                    cargo.to_owned(),
+                    Direction::XY.to_int(),
+                );
                 to_draw.draw_absolute(x + 50.0, y + 70.0, globals);
             }
             Card::Instruction(instr) => {
                 let cell = Cell::Instruction(match instr {
                     CardInstruction::Direct => Instruction::Direct(Direction::XY),
-                    CardInstruction::Rotate => Instruction::Rotate(Angle::LeftBack),
+                    CardInstruction::Rotate => Instruction::Rotate(Angle::Right),
                     CardInstruction::Shunt => Instruction::Shunt(Direction::XY),
+                    CardInstruction::Sort => Instruction::Sort {
+                        filter: ItemFilter::Any,
+                        matched: Direction::XY,
+                        unmatched: Direction::XZ,
+                    },
+                    CardInstruction::Switch => Instruction::Switch {
+                        directions: vec![Direction::XY, Direction::XZ],
+                        next: 0,
+                    },
+                    CardInstruction::Signal => Instruction::Signal { hold: Some(60 * 2) },
+                    CardInstruction::Light => Instruction::Light {
+                        green_first: true,
+                        green: true,
+                    },
+                    CardInstruction::Boost => Instruction::Boost,
+                    CardInstruction::Slow => Instruction::Slow,
+                    CardInstruction::Tunnel => Instruction::Tunnel {
+                        to: Coordinate::new(0, 0),
+                    },
+                    CardInstruction::Bridge => Instruction::Bridge {
+                        over: Direction::XY,
+                    },
+                    CardInstruction::Dump => Instruction::Dump { recycle: false },
+                    CardInstruction::Counter => Instruction::Counter {
+                        every: 3,
+                        straight: Direction::XY,
+                        redirect: Direction::XZ,
+                        count: 0,
+                    },
                 });
                 cell.draw_absolute(
                     x + 50.0 - HEX_WIDTH / 2.0,
@@ -124,40 +116,14 @@ impl Card {
                     WHITE,
                 );
             }
-        }
-    }
-}
-
-/// An instruction blueprint on a card
-#[derive(Clone)]
-pub enum CardInstruction {
-    /// Go in *this* direction
-    Direct,
-    /// Rotate relative by *this* much
-    Rotate,
-    /// Shunt in *this* direction
-    Shunt,
-}
-
-impl CardInstruction {
-    /// Sample a random CardInstruction
-    pub fn sample() -> Self {
-        let mut rng = rand::thread_rng();
-        let samples = [
-            CardInstruction::Direct,
-            CardInstruction::Direct,
-            CardInstruction::Direct,
-            CardInstruction::Shunt,
-        ];
-        samples[rng.gen_range(0..samples.len())].clone()
-    }
-
-    /// Turn this into an Instruction
-    pub fn to_instruction(&self, rotation: i32) -> Instruction {
-        match self {
-            CardInstruction::Direct => Instruction::Direct(Direction::from_int(rotation)),
-            CardInstruction::Rotate => Instruction::Rotate(Angle::from_int(rotation)),
-            CardInstruction::Shunt => Instruction::Shunt(Direction::from_int(rotation)),
+            Card::Warehouse => {
+                let cell = Cell::Warehouse(Warehouse::new(ItemFilter::Any));
+                cell.draw_absolute(
+                    x + 50.0 - HEX_WIDTH / 2.0,
+                    y + 70.0 - HEX_HEIGHT / 2.0,
+                    globals,
+                );
+            }
         }
     }
 }
@@ -167,38 +133,99 @@ pub struct SelectedCard {
     /// Original index of the card in hand, or None if it was from the board
     pub original_idx: Option<usize>,
     pub card: Card,
-    /// Absolute pixel position of the center
-    pub position: (f32, f32),
-    /// Rotation amount, also indicates other things...
-    /// It's what changes with the mouse wheel.
-    pub rotation: i32,
+    /// How it's set up. It's what changes with the mouse wheel.
+    pub dials: Dials,
+    /// The truck itself, if it was lifted off the board
+    pub truck: Option<Truck>,
 }
 
 impl SelectedCard {
+    /// Check if this card goes down in two places
+    pub fn needs_link(&self) -> bool {
+        matches!(self.card, Card::Instruction(CardInstruction::Tunnel))
+    }
+
+    /// Check if the first place is picked and it's waiting on the second
+    pub fn is_placing_link(&self) -> bool {
+        self.needs_link() && self.dials.link.is_some()
+    }
+
     pub fn draw(&self, cx: f32, cy: f32, globals: &Globals) {
         match &self.card {
-            Card::Truck { cargo } => {
-                let to_draw = Truck {
-                    cargo: cargo.to_owned(),
-                    speed: 0.0,
-                    facing: Direction::from_int(self.rotation),
-                    position: Coordinate::new(0, 0),
-                    move_progress: 0.0,
-                    out_of_bounds_immunity: false,
+            Card::Truck { cargo, class } => {
+                let to_draw = match &self.truck {
+                    Some(truck) => Truck {
+                        facing: Direction::from_int(self.dials.rotation),
+                        ..truck.clone()
+                    },
+                    None => Truck::from_rot(
+                        Coordinate::new(0, 0),
+                        *class,
+                        cargo.to_owned(),
+                        self.dials.rotation,
+                    ),
                 };
                 to_draw.draw_absolute(cx, cy, globals);
+
+                if let Some(truck) = &self.truck {
+                    drawutils::text(
+                        globals,
+                        &format!(
+                            "Drop on the board: move it for ${}\nDrop anywhere else: sell it for ${}",
+                            MOVE_TRUCK_FEE,
+                            truck.refund()
+                        ),
+                        14,
+                        cx + HEX_WIDTH / 2.0 + 4.0,
+                        cy,
+                        drawutils::TextAlign::Left,
+                    );
+                }
             }
             Card::Instruction(instr) => {
-                let cell = Cell::Instruction(match instr {
-                    CardInstruction::Direct => {
-                        Instruction::Direct(Direction::from_int(self.rotation))
-                    }
-                    CardInstruction::Rotate => Instruction::Rotate(Angle::from_int(self.rotation)),
-                    CardInstruction::Shunt => {
-                        Instruction::Shunt(Direction::from_int(self.rotation))
-                    }
-                });
+                if let Some(link) = self.dials.link {
+                    // The end that's already been picked
+                    let (lx, ly) = hex_center(link);
+                    Cell::Instruction(instr.to_instruction(self.dials)).draw(link, globals);
+                    draw_tunnel_link((lx, ly), (cx, cy));
+                }
+                let cell = Cell::Instruction(instr.to_instruction(self.dials));
                 cell.draw_absolute(cx - HEX_WIDTH / 2.0, cy - HEX_HEIGHT / 2.0, globals);
+
+                let hint = match instr {
+                    CardInstruction::Sort => Some(
+                        "Scroll: way for matches\nShift+scroll: way for the rest\nCtrl+scroll: item",
+                    ),
+                    CardInstruction::Switch => Some(
+                        "Scroll: first way\nShift+scroll: second way\nCtrl+scroll: third way (or none)",
+                    ),
+                    CardInstruction::Signal => {
+                        Some("Ctrl+scroll: seconds to wait (or until clear)")
+                    }
+                    CardInstruction::Light => Some("Ctrl+scroll: turn A or B"),
+                    CardInstruction::Rotate => Some("Scroll: how far to turn"),
+                    CardInstruction::Bridge => Some("Scroll: which way goes over the top"),
+                    CardInstruction::Dump => Some("Ctrl+scroll: dump or recycle"),
+                    CardInstruction::Counter => Some(
+                        "Scroll: way most trucks go\nShift+scroll: way every Nth truck goes\nCtrl+scroll: N",
+                    ),
+                    CardInstruction::Tunnel => Some(if self.dials.link.is_some() {
+                        "Click where the other end goes\nRight click: cancel"
+                    } else {
+                        "Drop where one end goes"
+                    }),
+                    _ => None,
+                };
+                if let Some(hint) = hint {
+                    drawutils::text(
+                        globals,
+                        hint,
+                        14,
+                        cx + HEX_WIDTH / 2.0 + 4.0,
+                        cy,
+                        drawutils::TextAlign::Left,
+                    );
+                }
             }
             Card::Cleanup => {
                 draw_texture(
@@ -208,6 +235,19 @@ impl SelectedCard {
                     WHITE,
                 );
             }
+            Card::Warehouse => {
+                let cell =
+                    Cell::Warehouse(Warehouse::new(ItemFilter::from_setting(self.dials.setting)));
+                cell.draw_absolute(cx - HEX_WIDTH / 2.0, cy - HEX_HEIGHT / 2.0, globals);
+                drawutils::text(
+                    globals,
+                    "Ctrl+scroll: item to keep (or anything)",
+                    14,
+                    cx + HEX_WIDTH / 2.0 + 4.0,
+                    cy,
+                    drawutils::TextAlign::Left,
+                );
+            }
         }
     }
 }
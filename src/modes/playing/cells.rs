@@ -1,23 +1,37 @@
-use super::economy::{Factory, Market};
 use crate::{
     drawutils::{self, BOARD_ORIGIN_X, BOARD_ORIGIN_Y, HEX_HEIGHT, HEX_RADIUS, HEX_WIDTH},
+    sim::cells::{Cell, Instruction},
     Globals,
 };
 
 use hex2d::{Angle, Coordinate, Direction, Spacing};
 
+use macroquad::prelude::Color;
+
 use std::f32::consts::TAU;
 
-/// A Cell is a spot on a board that trucks can drive on.
-#[derive(Debug)]
-pub enum Cell {
-    Empty,
-    /// Oh no, two trucks collided here.
-    Wreckage,
-    Instruction(Instruction),
-    Factory(Factory),
-    Market(Market),
-}
+/// Color for ways out that aren't lit up
+const ARROW_GRAY: Color = Color::new(200.0 / 255.0, 208.0 / 255.0, 209.0 / 255.0, 1.0);
+/// Color for the way matching trucks go
+const ARROW_GREEN: Color = Color::new(90.0 / 255.0, 170.0 / 255.0, 100.0 / 255.0, 1.0);
+/// Color for the way the next truck goes
+const ARROW_BLUE: Color = Color::new(80.0 / 255.0, 120.0 / 255.0, 200.0 / 255.0, 1.0);
+/// Color for signals and red lights
+const SIGNAL_RED: Color = Color::new(200.0 / 255.0, 50.0 / 255.0, 40.0 / 255.0, 1.0);
+/// Color for slowdowns
+const SLOW_ORANGE: Color = Color::new(230.0 / 255.0, 140.0 / 255.0, 40.0 / 255.0, 1.0);
+/// Color for tunnel mouths and the dots between them
+const TUNNEL_BROWN: Color = Color::new(120.0 / 255.0, 85.0 / 255.0, 60.0 / 255.0, 1.0);
+/// Pixels between the dots joining up the ends of a tunnel
+const TUNNEL_DOT_SPACING: f32 = 12.0;
+/// Color for the top of a bridge
+const BRIDGE_DECK: Color = Color::new(150.0 / 255.0, 150.0 / 255.0, 160.0 / 255.0, 1.0);
+/// Color for the railings along a bridge
+const BRIDGE_RAIL: Color = Color::new(90.0 / 255.0, 90.0 / 255.0, 100.0 / 255.0, 1.0);
+/// Color for dumps
+const DUMP_BROWN: Color = Color::new(110.0 / 255.0, 90.0 / 255.0, 50.0 / 255.0, 1.0);
+/// Color for the lamp in a traffic light that isn't on
+const LIGHT_OFF: Color = Color::new(70.0 / 255.0, 70.0 / 255.0, 70.0 / 255.0, 1.0);
 
 impl Cell {
     /// Draw this based on the hex position
@@ -89,6 +103,27 @@ impl Cell {
                 );
             }
 
+            Cell::Warehouse(warehouse) => {
+                draw_texture(textures.warehouse, cx, cy, WHITE);
+                draw_texture_ex(
+                    warehouse.filter.texture(globals),
+                    cx + 12.0,
+                    cy + 14.0,
+                    WHITE,
+                    DrawTextureParams {
+                        dest_size: Some(vec2(40.0, 40.0)),
+                        ..Default::default()
+                    },
+                );
+                drawutils::center_text(
+                    globals,
+                    &format!("{}/{}", warehouse.items.len(), warehouse.capacity),
+                    13,
+                    cx + 33.0,
+                    cy + 58.0,
+                );
+            }
+
             Cell::Wreckage => {
                 draw_texture(textures.wreckage, cx, cy, WHITE);
             }
@@ -113,6 +148,13 @@ impl Cell {
                             ..Default::default()
                         },
                     );
+                    drawutils::center_text(
+                        globals,
+                        angle_label(*rot),
+                        13,
+                        cx + HEX_WIDTH / 2.0,
+                        cy + HEX_HEIGHT / 2.0,
+                    );
                 }
                 Instruction::Direct(dir) => {
                     draw_texture_ex(
@@ -138,18 +180,217 @@ impl Cell {
                         },
                     );
                 }
+                Instruction::Sort {
+                    filter,
+                    matched,
+                    unmatched,
+                } => {
+                    let textures = &globals.assets.textures;
+                    draw_texture(textures.instr_hub, cx, cy, WHITE);
+                    // Draw the matching way last so it's on top if they're the same
+                    draw_half_arrow(*unmatched, cx, cy, ARROW_GRAY, globals);
+                    draw_half_arrow(*matched, cx, cy, ARROW_GREEN, globals);
+                    draw_texture_ex(
+                        filter.texture(globals),
+                        cx + HEX_WIDTH / 2.0 - 9.0,
+                        cy + HEX_HEIGHT / 2.0 - 9.0,
+                        WHITE,
+                        DrawTextureParams {
+                            dest_size: Some(vec2(18.0, 18.0)),
+                            ..Default::default()
+                        },
+                    );
+                }
+                Instruction::Switch { directions, next } => {
+                    draw_texture(globals.assets.textures.instr_hub, cx, cy, WHITE);
+                    // Light up the way the next truck goes
+                    for (idx, dir) in directions.iter().enumerate() {
+                        if idx != *next {
+                            draw_half_arrow(*dir, cx, cy, ARROW_GRAY, globals);
+                        }
+                    }
+                    if let Some(dir) = directions.get(*next) {
+                        draw_half_arrow(*dir, cx, cy, ARROW_BLUE, globals);
+                    }
+                    drawutils::center_text(
+                        globals,
+                        &(next + 1).to_string(),
+                        15,
+                        cx + HEX_WIDTH / 2.0,
+                        cy + HEX_HEIGHT / 2.0,
+                    );
+                }
+                Instruction::Signal { hold } => {
+                    let (mx, my) = (cx + HEX_WIDTH / 2.0, cy + HEX_HEIGHT / 2.0);
+                    draw_texture(globals.assets.textures.hex, cx, cy, WHITE);
+                    draw_poly(mx, my, 8, 15.0, 22.5, SIGNAL_RED);
+                    let label = match hold {
+                        Some(ticks) => format!("{}s", ticks / 60),
+                        None => "clear".to_owned(),
+                    };
+                    drawutils::center_text_color(globals, &label, 11, mx, my, WHITE);
+                }
+                Instruction::Light { green_first, green } => {
+                    let (mx, my) = (cx + HEX_WIDTH / 2.0, cy + HEX_HEIGHT / 2.0);
+                    draw_texture(globals.assets.textures.hex, cx, cy, WHITE);
+                    draw_rectangle(mx - 9.0, my - 17.0, 18.0, 34.0, DARKGRAY);
+                    let (red, go) = if *green {
+                        (LIGHT_OFF, GREEN)
+                    } else {
+                        (SIGNAL_RED, LIGHT_OFF)
+                    };
+                    draw_circle(mx, my - 8.0, 6.0, red);
+                    draw_circle(mx, my + 8.0, 6.0, go);
+                    drawutils::center_text(
+                        globals,
+                        if *green_first { "A" } else { "B" },
+                        13,
+                        mx + 18.0,
+                        my,
+                    );
+                }
+                Instruction::Boost | Instruction::Slow => {
+                    let (mx, my) = (cx + HEX_WIDTH / 2.0, cy + HEX_HEIGHT / 2.0);
+                    draw_texture(globals.assets.textures.hex, cx, cy, WHITE);
+                    let (color, label) = if *instruction == Instruction::Boost {
+                        (ARROW_GREEN, "+")
+                    } else {
+                        (SLOW_ORANGE, "-")
+                    };
+                    draw_circle(mx, my, 14.0, color);
+                    drawutils::center_text_color(globals, label, 24, mx, my, WHITE);
+                }
+                Instruction::Tunnel { .. } => {
+                    let (mx, my) = (cx + HEX_WIDTH / 2.0, cy + HEX_HEIGHT / 2.0);
+                    draw_texture(globals.assets.textures.hex, cx, cy, WHITE);
+                    draw_circle(mx, my, 17.0, TUNNEL_BROWN);
+                    draw_circle(mx, my + 3.0, 12.0, BLACK);
+                }
+                Instruction::Counter {
+                    every,
+                    straight,
+                    redirect,
+                    count,
+                } => {
+                    draw_texture(globals.assets.textures.instr_hub, cx, cy, WHITE);
+                    // Light up the way the next truck goes
+                    let (next, other) = if count + 1 >= *every {
+                        (redirect, straight)
+                    } else {
+                        (straight, redirect)
+                    };
+                    draw_half_arrow(*other, cx, cy, ARROW_GRAY, globals);
+                    draw_half_arrow(*next, cx, cy, ARROW_BLUE, globals);
+                    drawutils::center_text(
+                        globals,
+                        &format!("{}/{}", count, every),
+                        13,
+                        cx + HEX_WIDTH / 2.0,
+                        cy + HEX_HEIGHT / 2.0,
+                    );
+                }
+                Instruction::Dump { recycle } => {
+                    let (mx, my) = (cx + HEX_WIDTH / 2.0, cy + HEX_HEIGHT / 2.0);
+                    draw_texture(globals.assets.textures.hex, cx, cy, WHITE);
+                    let color = if *recycle { ARROW_GREEN } else { DUMP_BROWN };
+                    // A bin with a lid
+                    draw_rectangle(mx - 10.0, my - 8.0, 20.0, 22.0, color);
+                    draw_rectangle(mx - 13.0, my - 14.0, 26.0, 4.0, color);
+                    draw_rectangle(mx - 4.0, my - 18.0, 8.0, 4.0, color);
+                    if *recycle {
+                        drawutils::center_text_color(globals, "R", 14, mx, my + 3.0, WHITE);
+                    }
+                }
+                Instruction::Bridge { over } => {
+                    draw_texture(globals.assets.textures.hex, cx, cy, WHITE);
+                    draw_bridge_deck(*over, cx + HEX_WIDTH / 2.0, cy + HEX_HEIGHT / 2.0);
+                }
             },
         };
     }
 }
 
-/// Special instructions you can place on the board.
-#[derive(Debug)]
-pub enum Instruction {
-    /// Rotate the truck relative to its current direction
-    Rotate(Angle),
-    /// Direct the truck to the given direction
-    Direct(Direction),
-    /// Move the truck in the direction by one square
-    Shunt(Direction),
+/// Say how far a rotate instruction turns trucks, and which way
+fn angle_label(angle: Angle) -> &'static str {
+    match angle {
+        Angle::Forward => "0°",
+        Angle::Right => "60° R",
+        Angle::RightBack => "120° R",
+        Angle::Back => "180°",
+        Angle::LeftBack => "120° L",
+        Angle::Left => "60° L",
+    }
+}
+
+/// Draw the top of a bridge going `over`, centered at `x, y`
+pub fn draw_bridge_deck(over: Direction, x: f32, y: f32) {
+    use macroquad::prelude::*;
+
+    // Along the deck and across it
+    let (dy, dx) = (over.to_radians_pointy::<f32>() - TAU / 4.0).sin_cos();
+    let along = vec2(dx, dy) * HEX_WIDTH / 2.0;
+    let across = vec2(-dy, dx) * 13.0;
+    let center = vec2(x, y);
+
+    let corners = [
+        center - along - across,
+        center + along - across,
+        center + along + across,
+        center - along + across,
+    ];
+    draw_triangle(corners[0], corners[1], corners[2], BRIDGE_DECK);
+    draw_triangle(corners[0], corners[2], corners[3], BRIDGE_DECK);
+    draw_line(
+        corners[0].x,
+        corners[0].y,
+        corners[1].x,
+        corners[1].y,
+        3.0,
+        BRIDGE_RAIL,
+    );
+    draw_line(
+        corners[3].x,
+        corners[3].y,
+        corners[2].x,
+        corners[2].y,
+        3.0,
+        BRIDGE_RAIL,
+    );
+}
+
+/// Draw a dotted line between the two ends of a tunnel, given the pixel coordinates of their middles
+pub fn draw_tunnel_link(from: (f32, f32), to: (f32, f32)) {
+    use macroquad::prelude::*;
+
+    let (dx, dy) = (to.0 - from.0, to.1 - from.1);
+    let dots = ((dx * dx + dy * dy).sqrt() / TUNNEL_DOT_SPACING) as usize;
+    // Not on top of the mouths themselves
+    for idx in 2..dots.saturating_sub(1) {
+        let along = idx as f32 / dots as f32;
+        draw_circle(
+            from.0 + dx * along,
+            from.1 + dy * along,
+            2.5,
+            Color {
+                a: 0.6,
+                ..TUNNEL_BROWN
+            },
+        );
+    }
+}
+
+/// Draw an arrow from the middle of the hex with its corner at `cx, cy` out towards `dir`
+fn draw_half_arrow(dir: Direction, cx: f32, cy: f32, color: Color, globals: &Globals) {
+    use macroquad::prelude::*;
+
+    draw_texture_ex(
+        globals.assets.textures.instr_arrow_half,
+        cx,
+        cy,
+        color,
+        DrawTextureParams {
+            rotation: dir.to_radians_pointy::<f32>() - TAU / 4.0,
+            ..Default::default()
+        },
+    );
 }
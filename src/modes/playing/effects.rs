@@ -0,0 +1,89 @@
+use crate::{
+    drawutils::{self, BOARD_ORIGIN_X, BOARD_ORIGIN_Y, HEX_RADIUS},
+    sim::events::Event,
+    Globals,
+};
+
+use hex2d::{Coordinate, Spacing};
+use macroquad::prelude::Color;
+
+/// How long floating text sticks around, in seconds
+const FLOATER_LIFETIME: f32 = 1.5;
+/// How far floating text rises over its life, in pixels
+const FLOATER_RISE: f32 = 30.0;
+
+/// Little bits of text that pop up over the board when something happens.
+pub struct Effects {
+    floaters: Vec<Floater>,
+}
+
+struct Floater {
+    text: String,
+    color: Color,
+    coord: Coordinate,
+    /// Seconds it's been alive
+    age: f32,
+}
+
+impl Effects {
+    pub fn new() -> Self {
+        Self {
+            floaters: Vec::new(),
+        }
+    }
+
+    /// Pop up text for whatever in here is worth mentioning.
+    pub fn push_events(&mut self, events: &[Event]) {
+        use macroquad::prelude::*;
+
+        for event in events {
+            let (text, color) = match event {
+                Event::Delivered { price, .. } | Event::Recycled { price, .. } => {
+                    (format!("+${}", price), DARKGREEN)
+                }
+                Event::Dumped { fee, .. } => (format!("-${}", fee), DARKGRAY),
+                Event::Spoiled { item, .. } => (format!("{} went off!", item.name()), DARKGRAY),
+                Event::BadSell { .. } => (format!("Wrong item! +{} tax", event.tax()), RED),
+                Event::ShortSell { .. } => (format!("Empty! +{} tax", event.tax()), RED),
+                Event::Overload { .. } => (format!("Overload! +{} tax", event.tax()), RED),
+                Event::Crash { .. } => (format!("Crash! +{} tax", event.tax()), RED),
+                Event::HitWreckage { .. } | Event::DroveOffBoard { .. } => {
+                    (format!("Wrecked! +{} tax", event.tax()), RED)
+                }
+                _ => continue,
+            };
+            self.floaters.push(Floater {
+                text,
+                color,
+                coord: event.coord(),
+                age: 0.0,
+            });
+        }
+    }
+
+    pub fn update(&mut self, frame_time: f32) {
+        self.floaters.retain_mut(|floater| {
+            floater.age += frame_time;
+            floater.age < FLOATER_LIFETIME
+        });
+    }
+
+    pub fn draw(&self, globals: &Globals) {
+        for floater in self.floaters.iter() {
+            let progress = floater.age / FLOATER_LIFETIME;
+            let (x, y) = floater.coord.to_pixel(Spacing::PointyTop(HEX_RADIUS));
+            let color = Color {
+                a: 1.0 - progress,
+                ..floater.color
+            };
+            drawutils::center_text_color(
+                globals,
+                &floater.text,
+                18,
+                x + BOARD_ORIGIN_X,
+                y + BOARD_ORIGIN_Y - FLOATER_RISE * progress,
+                color,
+            );
+        }
+    }
+}
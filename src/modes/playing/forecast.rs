@@ -0,0 +1,86 @@
+use crate::{
+    drawutils::{BOARD_ORIGIN_X, BOARD_ORIGIN_Y, HEX_RADIUS},
+    sim::{
+        forecast::{Forecast, Route},
+        trucks::TruckId,
+    },
+};
+
+use hex2d::{Coordinate, Spacing};
+use macroquad::prelude::Color;
+
+/// Colors to tell routes apart, picked by truck ID
+const ROUTE_COLORS: [(u8, u8, u8); 6] = [
+    (40, 110, 220),
+    (230, 130, 20),
+    (40, 170, 80),
+    (160, 60, 200),
+    (20, 170, 180),
+    (200, 60, 120),
+];
+
+impl Forecast {
+    /// Draw every route as a line fading out the further ahead it goes,
+    /// with likely crashes and wrecks marked in red.
+    pub fn draw(&self) {
+        for route in self.routes.iter() {
+            route.draw(route_color(route.truck), 5.0);
+        }
+        for &coord in self.conflicts.iter() {
+            draw_conflict(coord);
+        }
+    }
+}
+
+impl Route {
+    /// Draw this route as a line fading out the further ahead it goes,
+    /// with a cross where it ends badly.
+    pub fn draw(&self, color: Color, thickness: f32) {
+        use macroquad::prelude::*;
+
+        let segments = self.hexes.len().saturating_sub(1).max(1);
+        for (idx, pair) in self.hexes.windows(2).enumerate() {
+            let (x1, y1) = hex_center(pair[0].1);
+            let (x2, y2) = hex_center(pair[1].1);
+            let opacity = 0.9 - 0.8 * idx as f32 / segments as f32;
+            draw_line(
+                x1,
+                y1,
+                x2,
+                y2,
+                thickness,
+                Color {
+                    a: color.a * opacity,
+                    ..color
+                },
+            );
+        }
+
+        if let Some(fate) = &self.fate {
+            let (x, y) = hex_center(fate.coord());
+            let size = HEX_RADIUS * 0.3;
+            draw_line(x - size, y - size, x + size, y + size, 4.0, RED);
+            draw_line(x - size, y + size, x + size, y - size, 4.0, RED);
+        }
+    }
+}
+
+/// Ring a hex where trucks look set to crash
+pub fn draw_conflict(coord: Coordinate) {
+    use macroquad::prelude::*;
+
+    let (x, y) = hex_center(coord);
+    draw_circle_lines(x, y, HEX_RADIUS * 0.7, 4.0, RED);
+}
+
+/// Color for drawing a truck's route
+pub fn route_color(truck: TruckId) -> Color {
+    let (r, g, b) = ROUTE_COLORS[truck.0 as usize % ROUTE_COLORS.len()];
+    Color::from_rgba(r, g, b, 255)
+}
+
+/// Screen position of the center of a hex
+pub fn hex_center(coord: Coordinate) -> (f32, f32) {
+    let (x, y) = coord.to_pixel(Spacing::PointyTop(HEX_RADIUS));
+    (x + BOARD_ORIGIN_X, y + BOARD_ORIGIN_Y)
+}
@@ -1,308 +1,376 @@
 mod cards;
 mod cells;
 mod economy;
+pub(crate) mod effects;
+mod forecast;
+mod preview;
+mod time_controls;
 mod trucks;
 
 use self::{
-    cards::{Card, SelectedCard},
-    cells::{Cell, Instruction},
-    trucks::{Treads, Truck},
+    cards::SelectedCard,
+    cells::{draw_bridge_deck, draw_tunnel_link},
+    effects::Effects,
+    forecast::hex_center,
+    preview::PlacementPreview,
+    time_controls::TimeControls,
 };
 use crate::{
     drawutils::{
         self, BOARD_ORIGIN_X, BOARD_ORIGIN_Y, CARD_PADDING, CARD_TOP_POS, CARD_WIDTH, HEX_RADIUS,
         HEX_WIDTH,
     },
+    sim::{
+        cards::{Card, CardInstruction, Dials},
+        cells::{Cell, Instruction},
+        forecast::Forecast,
+        save,
+        trucks::Truck,
+        Board, Command, PlayerInfo, Simulation, HAND_SIZE, MOVE_TRUCK_FEE, TAX_TIMER,
+    },
     GameMode, Globals, Transition,
 };
-use cards::CardInstruction;
 
 use drawutils::{TextAlign, HUD_LEFT_POS};
-use economy::{Factory, Item, ItemFilter, Market, Pricer};
-use hex2d::{Angle, Coordinate, Direction, Spacing, Spin};
+use hex2d::{Coordinate, Spacing};
 use macroquad::prelude::{
-    is_key_down, is_key_pressed, is_mouse_button_down, mouse_position, mouse_wheel, KeyCode,
-    MouseButton,
+    get_frame_time, is_key_down, is_key_pressed, is_mouse_button_down, is_mouse_button_pressed,
+    is_mouse_button_released, mouse_position, mouse_wheel, KeyCode, MouseButton,
 };
-use rand::Rng;
 
-use std::{
-    cell,
-    collections::{hash_map::Entry, HashMap},
-    f32::consts::TAU,
-};
+use std::f32::consts::TAU;
 
 use super::ModeLose;
 
-/// Tax happens every this many frames
-const TAX_TIMER: u64 = 60 * 20;
-/// Tax increase from colliding with a wreckage or going off the board
-const TAX_COLLISION: u32 = 5;
-/// Tax increase from two trucks crashing into each other
-const TAX_CRASH: u32 = 10;
-/// Tax increase from picking up a good when full
-const TAX_OVERLOAD: u32 = 1;
-/// Tax increase from bringing an empty truck to a market
-const TAX_SHORTSELL: u32 = 2;
-/// Tax increase from bringing the wrong thing to market
-const TAX_BAD_SELL: u32 = 5;
-
-/// Radius two trucks must be within each other to collide
-const TRUCK_CRASH_RADIUS: f32 = 0.5;
-
-/// Board-pixel distance between the center of the truck and where treads ought to be drawn
-const TREAD_OFFSET: f32 = (10.0 / 64.0) * (64.0 / HEX_WIDTH);
-/// How long a tread lives for
-const TREAD_LIFETIME: u64 = 300;
-/// Number of frames at which the tread begins to face
+/// Number of ticks at which the tread begins to face
 const TREAD_FADE_TIME: u64 = 60;
+/// How many hexes ahead the route overlay looks
+const ROUTE_FORECAST_HEXES: usize = 8;
+/// How close to a truck's middle (in hexes) a click has to be to grab it
+const TRUCK_GRAB_RADIUS: f32 = 0.4;
+/// How long after a placement it can be taken back, when undoing is on a timer
+const UNDO_TICKS: u64 = 60 * 5;
+
+/// When the player is allowed to undo (or redo) something
+#[derive(Clone, Copy)]
+enum UndoWindow {
+    /// Up to this many ticks of game time after it was done
+    GameTime(u64),
+    /// Any time, but only while the game is paused
+    WhilePaused,
+}
 
-/// Max hand size
-pub const HAND_SIZE: usize = 5;
+impl UndoWindow {
+    fn allows(&self, done_tick: u64, now: u64, paused: bool) -> bool {
+        match self {
+            UndoWindow::GameTime(ticks) => now <= done_tick + ticks,
+            UndoWindow::WhilePaused => paused,
+        }
+    }
 
-pub struct ModePlaying {
-    board: Board,
-    player_info: PlayerInfo,
-    /// How many frames this mode has been alive
-    frames_elapsed: u64,
-}
+    /// The other kind of window
+    fn toggled(self) -> Self {
+        match self {
+            UndoWindow::GameTime(_) => UndoWindow::WhilePaused,
+            UndoWindow::WhilePaused => UndoWindow::GameTime(UNDO_TICKS),
+        }
+    }
 
-struct Board {
-    /// Maps coordinates to cells.
-    /// Any empty cells are impassable and shouldn't be driven into.
-    cells: HashMap<Coordinate, Cell>,
-    /// All the trucks
-    trucks: Vec<Truck>,
-    /// All the treads.
-    /// TODO this might be a lot of things to keep track of?
-    treads: Vec<Treads>,
-    /// Radius of the board proper (not counting the buildings on the outsides)
-    radius: usize,
+    /// Say when undoing works, for the controls text
+    fn describe(self) -> String {
+        match self {
+            UndoWindow::GameTime(ticks) => format!("within {}s", ticks / 60),
+            UndoWindow::WhilePaused => "while paused".to_string(),
+        }
+    }
 }
 
-struct PlayerInfo {
-    /// How much money I have
-    money: u32,
-    /// Current tax rate
-    tax: u32,
-    /// The most amount of money I've ever had
-    highscore: u32,
-
-    /// The cards the player has in hand
-    hand: Vec<Card>,
-    /// The currently selected card
+pub struct ModePlaying {
+    sim: Simulation,
+    time: TimeControls,
+    effects: Effects,
+    /// When placements can still be taken back
+    undo_window: UndoWindow,
+    /// Whether to draw where the trucks are headed
+    show_routes: bool,
+    /// Where the trucks are headed, with the tick and number of commands it was worked out at.
+    /// Only redone when one of those changes.
+    routes: Option<(u64, usize, Forecast)>,
+    /// The card the player is dragging around
     selected_card: Option<SelectedCard>,
+    /// What dropping the selected card would do
+    preview: Option<PlacementPreview>,
+    /// Something to tell the player, like that saving didn't work
+    notice: Option<String>,
 }
 
 impl ModePlaying {
-    // sHUT UP CLIPPY AAAAAAAAAAAAAAAAAAAAAAAAAAAA
-    #[allow(clippy::new_without_default)]
-    pub fn new() -> Self {
-        let radius = 5;
-        let mut board = Board {
-            cells: {
-                let mut map = HashMap::new();
-                for coord in Coordinate::new(0, 0).range_iter(radius as i32) {
-                    map.insert(coord, Cell::Empty);
-                }
-                map
-            },
-            trucks: vec![],
-            treads: vec![],
-            radius,
-        };
-
-        // Generate stuff
-        for idx in 0..6 {
-            board.add_building((idx + 1) * 250);
-        }
-
-        let player_info = PlayerInfo {
-            hand: Card::starting_hand(),
-            highscore: 0,
-            money: 100,
-            tax: 0,
-            selected_card: None,
-        };
+    pub fn new(seed: u64) -> Self {
+        Self::from_sim(Simulation::new(seed))
+    }
 
+    /// Pick up a game that's already going, like one from a save file.
+    pub fn from_sim(sim: Simulation) -> Self {
         Self {
-            board,
-            player_info,
-            frames_elapsed: 0,
+            sim,
+            time: TimeControls::new(),
+            effects: Effects::new(),
+            undo_window: UndoWindow::GameTime(UNDO_TICKS),
+            show_routes: false,
+            routes: None,
+            selected_card: None,
+            preview: None,
+            notice: None,
         }
     }
 
-    pub fn update(&mut self, globals: &mut Globals) -> Transition {
-        let (dmoney, dtax) = self.board.update(self.frames_elapsed, globals);
-        self.player_info.money += dmoney;
-        self.player_info.tax += dtax;
-        self.player_info.highscore += dmoney;
-
-        if self.frames_elapsed % TAX_TIMER == 0 && self.frames_elapsed != 0 && self.apply_tax() {
-            return Transition::Swap(GameMode::Lose(ModeLose::new(self.player_info.highscore)));
+    pub fn update(&mut self, _globals: &mut Globals) -> Transition {
+        for _ in 0..self.time.update() {
+            let events = self.sim.tick();
+            self.effects.push_events(&events);
+            if self.sim.game_over {
+                return self.lose();
+            }
         }
+        self.effects.update(get_frame_time());
 
         // Input
-        match &mut self.player_info.selected_card {
+        if is_key_pressed(KeyCode::R) {
+            self.show_routes = !self.show_routes;
+        }
+        if is_key_pressed(KeyCode::U) {
+            self.undo_window = self.undo_window.toggled();
+        }
+        if is_key_pressed(KeyCode::F) {
+            self.sim.apply(Command::ToggleSafeFollowing);
+        }
+        match &mut self.selected_card {
             None => {
                 use macroquad::prelude::*;
+                if is_key_pressed(KeyCode::Escape) {
+                    // Save & quit
+                    match save::save(&self.sim) {
+                        Ok(()) => return Transition::Quit,
+                        Err(err) => self.notice = Some(format!("Couldn't save: {}", err)),
+                    }
+                }
+
+                let ctrl = is_key_down(KeyCode::LeftControl) || is_key_down(KeyCode::RightControl);
+                if ctrl && is_key_pressed(KeyCode::Z) {
+                    self.undo_redo(Command::Undo);
+                } else if ctrl && is_key_pressed(KeyCode::Y) {
+                    self.undo_redo(Command::Redo);
+                }
+
                 if is_mouse_button_pressed(MouseButton::Left) {
                     // Check if i'm in a correct card zone
                     let (mouse_x, mouse_y) = mouse_position();
                     let card_idx = mouse_x / (CARD_WIDTH + CARD_PADDING);
-                    if card_idx > 1.0 && mouse_y >= CARD_TOP_POS {
+                    if self.time.contains(mouse_x, mouse_y) {
+                        // the time controls got this one
+                    } else if card_idx > 1.0 && mouse_y >= CARD_TOP_POS {
                         let card_idx = card_idx as usize - 1;
-                        if card_idx < self.player_info.hand.len() {
+                        if card_idx < self.sim.player_info.hand.len() {
                             // noice we select this
-                            let card = self.player_info.hand[card_idx].clone();
+                            let card = self.sim.player_info.hand[card_idx].clone();
                             let rotation = match &card {
                                 Card::Truck { .. } => 2,
-                                Card::Instruction(CardInstruction::Rotate) => 4,
+                                Card::Instruction(CardInstruction::Rotate) => 0,
                                 Card::Instruction(CardInstruction::Direct) => 2,
                                 Card::Instruction(CardInstruction::Shunt) => 2,
+                                Card::Instruction(CardInstruction::Sort) => 2,
+                                Card::Instruction(CardInstruction::Switch) => 2,
+                                Card::Instruction(CardInstruction::Signal) => 0,
+                                Card::Instruction(CardInstruction::Light) => 0,
+                                Card::Instruction(CardInstruction::Boost) => 0,
+                                Card::Instruction(CardInstruction::Slow) => 0,
+                                Card::Instruction(CardInstruction::Tunnel) => 0,
+                                Card::Instruction(CardInstruction::Bridge) => 2,
+                                Card::Instruction(CardInstruction::Dump) => 0,
+                                Card::Instruction(CardInstruction::Counter) => 2,
                                 Card::Cleanup => 0,
+                                Card::Warehouse => 0,
+                            };
+                            let dials = Dials {
+                                rotation,
+                                alt_rotation: rotation - 1,
+                                setting: 0,
+                                link: None,
                             };
 
-                            self.player_info.selected_card = Some(SelectedCard {
+                            self.selected_card = Some(SelectedCard {
                                 original_idx: Some(card_idx),
                                 card,
-                                rotation,
-                                position: mouse_position(),
+                                dials,
+                                truck: None,
                             });
                         } else if card_idx == HAND_SIZE {
                             // draw a new card
-                            if self.apply_tax() {
-                                return Transition::Swap(GameMode::Lose(ModeLose::new(
-                                    self.player_info.highscore,
-                                )));
+                            self.sim.apply(Command::ForceTax);
+                            if self.sim.game_over {
+                                return self.lose();
                             }
                         }
-                    } else {
-                        let coord = Coordinate::from_pixel(
-                            mouse_x - BOARD_ORIGIN_X,
-                            mouse_y - BOARD_ORIGIN_Y,
-                            Spacing::PointyTop(HEX_RADIUS),
-                        );
-                        let cell = self.board.cells.get(&coord);
-                        if let Some(Cell::Instruction(..)) = cell {
-                            // there must be a better way to do this
-                            let instr = match self.board.cells.insert(coord, Cell::Empty) {
-                                Some(Cell::Instruction(i)) => i,
-                                _ => unreachable!(),
-                            };
-                            let (card_instr, rotation) = match &instr {
-                                Instruction::Rotate(angle) => {
-                                    (CardInstruction::Rotate, angle.to_int())
-                                }
-                                Instruction::Direct(dir) => (CardInstruction::Direct, dir.to_int()),
-                                Instruction::Shunt(dir) => (CardInstruction::Shunt, dir.to_int()),
-                            };
-                            self.player_info.selected_card = Some(SelectedCard {
-                                card: Card::Instruction(card_instr),
-                                rotation,
+                    } else if let Some(truck) = hovered_truck(&self.sim.board, self.time.alpha()) {
+                        let truck = truck.clone();
+                        if self.sim.apply(Command::LiftTruck { truck: truck.id }) {
+                            self.selected_card = Some(SelectedCard {
+                                card: Card::Truck {
+                                    cargo: truck.cargo.first().cloned(),
+                                    class: truck.class,
+                                },
+                                dials: Dials {
+                                    rotation: truck.facing.to_int(),
+                                    ..Default::default()
+                                },
                                 original_idx: None,
-                                position: mouse_position(),
+                                truck: Some(truck),
                             });
                         }
+                    } else {
+                        let coord = hovered_hex();
+                        if let Some(Cell::Instruction(instr)) = self.sim.board.cells.get(&coord) {
+                            let (card_instr, dials) = CardInstruction::from_instruction(instr);
+                            if self.sim.apply(Command::PickUp { coord }) {
+                                self.selected_card = Some(SelectedCard {
+                                    card: Card::Instruction(card_instr),
+                                    dials,
+                                    original_idx: None,
+                                    truck: None,
+                                });
+                            }
+                        }
                     }
                 }
             }
             Some(selected) => {
-                if !is_mouse_button_down(MouseButton::Left) {
-                    enum CardStatus {
-                        Place,
-                        ReturnToHand,
-                        Discard,
-                    };
-
+                // The second end of a tunnel goes down with a click, not a drag
+                let placing_link = selected.is_placing_link();
+                let dropped = if placing_link {
+                    is_mouse_button_released(MouseButton::Left)
+                } else {
+                    !is_mouse_button_down(MouseButton::Left)
+                };
+
+                if placing_link
+                    && (is_mouse_button_pressed(MouseButton::Right)
+                        || is_key_pressed(KeyCode::Escape))
+                {
+                    // never mind
+                    self.selected_card = None;
+                    self.preview = None;
+                } else if dropped
+                    && selected.needs_link()
+                    && !placing_link
+                    && self.sim.board.can_hold_instruction(hovered_hex())
+                {
+                    // That's one end down, now for the other
+                    selected.dials.link = Some(hovered_hex());
+                    self.preview = None;
+                } else if dropped {
                     // check if i'm in the grid
-                    let board_x = mouse_position().0 - BOARD_ORIGIN_X;
-                    let board_y = mouse_position().1 - BOARD_ORIGIN_Y;
-                    let coord =
-                        Coordinate::from_pixel(board_x, board_y, Spacing::PointyTop(HEX_RADIUS));
-
-                    let card_status =
-                        if coord.distance(Coordinate::new(0, 0)) <= self.board.radius as i32 {
-                            // put it there!
-                            match &selected.card {
-                                Card::Instruction(card_instr) => {
-                                    if matches!(
-                                        self.board.cells.get(&coord),
-                                        Some(Cell::Empty) | Some(Cell::Instruction(_))
-                                    ) {
-                                        // nice
-                                        let instr = card_instr.to_instruction(selected.rotation);
-                                        self.board.cells.insert(coord, Cell::Instruction(instr));
-                                        CardStatus::Place
-                                    } else {
-                                        CardStatus::ReturnToHand
-                                    }
-                                }
-                                Card::Truck { cargo } => {
-                                    self.board.trucks.push(Truck::from_rot(
-                                        coord,
-                                        cargo.clone(),
-                                        selected.rotation,
-                                    ));
-                                    CardStatus::Place
-                                }
-                                Card::Cleanup => {
-                                    if self.board.cells.contains_key(&coord) {
-                                        self.board.cells.insert(coord, Cell::Empty);
-                                        CardStatus::Place
-                                    } else {
-                                        CardStatus::ReturnToHand
-                                    }
-                                }
-                            }
-                        } else {
-                            let mouse_x = mouse_position().0;
-                            let card_idx = mouse_x / (CARD_WIDTH + CARD_PADDING);
-                            // We "successfully" throw the card away if we're in the trash zone
-                            if card_idx < 1.0 {
-                                CardStatus::Discard
+                    let coord = hovered_hex();
+                    let on_board = self.sim.board.is_on_board(coord);
+
+                    let mut keep_holding = false;
+                    match selected.original_idx {
+                        Some(hand_idx) => {
+                            if on_board {
+                                // put it there!
+                                // if we can't, it just goes back to the hand.
+                                self.sim.apply(Command::PlayCard {
+                                    hand_idx,
+                                    coord,
+                                    dials: selected.dials,
+                                });
                             } else {
-                                CardStatus::ReturnToHand
-                            }
-                        };
-
-                    match card_status {
-                        CardStatus::Place => {
-                            if selected.card.cost() <= self.player_info.money {
-                                // Nice we play the card
-                                // Remove the card
-                                if let Some(original_idx) = selected.original_idx {
-                                    self.player_info.hand.remove(original_idx);
-                                    // Pay money if we had an original idx, meaning it came from the hand
-                                    self.player_info.money -= selected.card.cost();
+                                let mouse_x = mouse_position().0;
+                                let card_idx = mouse_x / (CARD_WIDTH + CARD_PADDING);
+                                // We throw the card away if we're in the trash zone
+                                if card_idx < 1.0 {
+                                    self.sim.apply(Command::Discard { hand_idx });
                                 }
                             }
                         }
-                        CardStatus::Discard => {
-                            // Remove the card
-                            if let Some(original_idx) = selected.original_idx {
-                                self.player_info.hand.remove(original_idx);
+                        None if selected.truck.is_some() => {
+                            // Move it, or sell it if it's dropped off the board
+                            if !self.sim.apply(Command::PutDownTruck {
+                                coord: if on_board { Some(coord) } else { None },
+                                rotation: selected.dials.rotation,
+                            }) {
+                                // Can't pay to move it, so hang on until it's sold or paid for
+                                self.notice =
+                                    Some(format!("Moving a truck costs ${}", MOVE_TRUCK_FEE));
+                                keep_holding = true;
                             }
                         }
-                        CardStatus::ReturnToHand => {
-                            // do nothing
+                        None => {
+                            // It came off the board, so it goes back on the board or nowhere
+                            self.sim.apply(Command::PutDown {
+                                coord: if on_board { Some(coord) } else { None },
+                                dials: selected.dials,
+                            });
                         }
                     }
-                    // in any case stop selecting
-                    self.player_info.selected_card = None;
+                    // otherwise stop selecting
+                    if !keep_holding {
+                        self.selected_card = None;
+                        self.preview = None;
+                    }
                 } else {
+                    // Shift and ctrl pick which dial to turn
+                    let dial =
+                        if is_key_down(KeyCode::LeftShift) || is_key_down(KeyCode::RightShift) {
+                            &mut selected.dials.alt_rotation
+                        } else if is_key_down(KeyCode::LeftControl)
+                            || is_key_down(KeyCode::RightControl)
+                        {
+                            &mut selected.dials.setting
+                        } else {
+                            &mut selected.dials.rotation
+                        };
                     // mouse_wheel: up is positive, down is negative.
-                    let scroll = mouse_wheel().1;
+                    // Some platforms turn shift+scroll sideways, so take that too.
+                    let scroll = match mouse_wheel() {
+                        (sideways, 0.0) => sideways,
+                        (_, scroll) => scroll,
+                    };
                     if scroll > 0.0 || is_key_pressed(KeyCode::A) {
-                        selected.rotation -= 1;
+                        *dial -= 1;
                     } else if scroll < 0.0 || is_key_pressed(KeyCode::D) {
-                        selected.rotation += 1;
+                        *dial += 1;
+                    }
+
+                    // Show what dropping it here would do
+                    let coord = hovered_hex();
+                    let stale = match &self.preview {
+                        Some(preview) => !preview.is_for(coord, selected.dials, self.sim.ticks),
+                        None => true,
+                    };
+                    if stale {
+                        self.preview =
+                            PlacementPreview::new(&self.sim.board, self.sim.ticks, selected, coord);
                     }
                 }
             }
         }
 
-        self.frames_elapsed += 1;
+        if self.show_routes {
+            let ticks = self.sim.ticks;
+            let commands = self.sim.recording.commands.len();
+            let stale = match &self.routes {
+                Some((old_ticks, old_commands, _)) => {
+                    (*old_ticks, *old_commands) != (ticks, commands)
+                }
+                None => true,
+            };
+            if stale {
+                let routes = self.sim.board.forecast(ticks, ROUTE_FORECAST_HEXES);
+                self.routes = Some((ticks, commands, routes));
+            }
+        } else {
+            self.routes = None;
+        }
 
         Transition::None
     }
@@ -312,233 +380,124 @@ impl ModePlaying {
 
         clear_background(Color::from_rgba(250, 252, 255, 255));
 
-        self.board.draw(globals);
-        self.player_info.draw(self.frames_elapsed, globals);
-    }
-
-    // return `true` to quit
-    fn apply_tax(&mut self) -> bool {
-        match self.player_info.money.checked_sub(self.player_info.tax) {
-            Some(it) => self.player_info.money = it,
-            None => {
-                // oh no we ran out of money :(
-                return true;
-            }
+        self.sim.board.draw(self.time.alpha(), globals);
+        if let Some((_, _, routes)) = &self.routes {
+            routes.draw();
         }
-
-        if self.player_info.hand.len() < HAND_SIZE {
-            self.player_info.hand.push(Card::generate());
+        if let Some(preview) = &self.preview {
+            preview.draw(globals);
         }
-        self.player_info.tax += 1;
-        false
-    }
-}
-
-impl Board {
-    /// Update the board and trucks.
-    ///
-    /// Return `(delta_money, delta_tax)`
-    fn update(&mut self, frames_elapsed: u64, globals: &mut Globals) -> (u32, u32) {
-        let mut money = 0;
-        let mut tax = 0;
-
-        // Update all my truccs and remove the collided ones
-        let cells = &mut self.cells;
-        let treads = &mut self.treads;
-        self.trucks
-            .drain_filter(|truck| {
-                truck.move_progress += truck.speed;
-
-                // Where the truck is going
-                let target = truck.position + truck.facing;
-                // Where the center of the truck is
-                let realpos = truck.get_hex();
-
-                if truck.move_progress > 0.5 {
-                    // we crossed a hex, no longer immune
-                    truck.out_of_bounds_immunity = false;
-                }
-
-                // Add treads
-                if frames_elapsed % 3 == 0 {
-                    let truck_pos = truck.get_xy();
-                    let (dy, dx) = (truck.facing.to_radians_pointy::<f32>() - TAU / 4.0).sin_cos();
-                    let pos = (
-                        truck_pos.0 + dx * TREAD_OFFSET,
-                        truck_pos.1 + dy * TREAD_OFFSET,
-                    );
-                    treads.push(Treads {
-                        pos,
-                        facing: truck.facing,
-                        lifetime: TREAD_LIFETIME,
-                    });
-                }
-
-                if !Board::is_passable(cells, realpos, truck.out_of_bounds_immunity) {
-                    // oh no, clobber this position into wreckage
-                    match cells.get(&truck.position) {
-                        Some(Cell::Factory(..)) | Some(Cell::Market(..)) => {
-                            cells.remove(&truck.position);
-                        }
-                        Some(..) => {
-                            cells.insert(truck.position, Cell::Wreckage);
-                        }
-                        _ => {}
-                    }
-
-                    tax += TAX_COLLISION;
-                    // delt trucc
-                    return true;
-                }
-
-                if truck.move_progress >= 1.0 {
-                    // We're on the center of the next coord
-                    truck.position = target;
-                    truck.move_progress = 0.0;
-
-                    // Take a special action?
-                    let cell = cells.entry(truck.position);
-                    if let Entry::Occupied(mut occupied) = cell {
-                        match occupied.get_mut() {
-                            Cell::Factory(factory) => {
-                                if truck.cargo.is_some() {
-                                    // uh-oh
-                                    tax += TAX_OVERLOAD;
-                                }
-                                truck.cargo = Some(factory.product.clone());
-                                factory.stock -= 1;
-                                if factory.stock == 0 {
-                                    // clear the factory
-                                    occupied.remove();
-                                    // and it's ok to drive over empty for now
-                                    truck.out_of_bounds_immunity = true;
-                                }
-                                truck.facing = truck.facing + Angle::Back;
-                            }
-                            Cell::Market(market) => {
-                                match &truck.cargo {
-                                    None => {
-                                        // uh-oh
-                                        tax += TAX_SHORTSELL;
-                                    }
-                                    Some(item) => {
-                                        if market.request.matches(item) {
-                                            // noice
-                                            money += market.prices.sample();
-                                            market.demand -= 1;
-                                            if market.demand == 0 {
-                                                occupied.remove();
-                                                truck.out_of_bounds_immunity = true;
-                                            }
-                                        } else {
-                                            // oh no
-                                            tax += TAX_BAD_SELL;
-                                        }
-                                        truck.cargo = None;
-                                    }
-                                }
-                                truck.facing = truck.facing + Angle::Back;
-                            }
-                            Cell::Instruction(instruction) => match *instruction {
-                                Instruction::Rotate(rot) => {
-                                    truck.facing = truck.facing + rot;
-                                }
-                                Instruction::Direct(dir) => {
-                                    truck.facing = dir;
-                                }
-                                Instruction::Shunt(shunt) => {
-                                    let target = truck.position + shunt;
-                                    if !Board::is_passable(
-                                        cells,
-                                        target,
-                                        truck.out_of_bounds_immunity,
-                                    ) {
-                                        // oh no, clobber this position into wreckage
-                                        if cells.contains_key(&truck.position) {
-                                            cells.insert(truck.position, Cell::Wreckage);
-                                        }
-                                        tax += TAX_COLLISION;
-                                        // delt trucc
-                                        return true;
-                                    } else {
-                                        truck.position = target;
-                                    }
-                                }
-                            },
-                            Cell::Empty => {}
-                            Cell::Wreckage => {
-                                // :HOW:
-                                println!("A truck has fallen into the wreckage in Error City!");
-                                return true;
-                            }
-                        }
-                    }
-                }
+        self.effects.draw(globals);
+        self.sim
+            .player_info
+            .draw(self.selected_card.as_ref(), self.sim.ticks, globals);
+        self.time.draw(globals);
+        drawutils::text(
+            globals,
+            &format!("Seed: {}", self.sim.seed),
+            14,
+            HUD_LEFT_POS,
+            CARD_TOP_POS + 130.0,
+            TextAlign::Left,
+        );
 
-                // otherwise keep
-                false
-            })
-            .for_each(drop); // get rid of bad ones
-
-        // Update treads
-        self.treads
-            .drain_filter(|tread| {
-                if tread.lifetime == 0 {
-                    // drop it
-                    true
+        drawutils::text(
+            globals,
+            &format!(
+                "Ctrl+Z/Y: undo/redo ({})  U: change when  R: show routes\nF: safe following ({})  Esc: save & quit",
+                self.undo_window.describe(),
+                if self.sim.board.safe_following {
+                    "on"
                 } else {
-                    tread.lifetime -= 1;
-                    false
+                    "off"
                 }
-            })
-            .for_each(drop);
-
-        // Check for collisions
-        let mut collided_truck_idxes = Vec::new();
-        for (idx, truck) in self.trucks.iter().enumerate() {
-            let (x, y) = truck.get_xy();
-            // prevent collisions with self
-            for other in self.trucks.iter().skip(idx + 1) {
-                let (ox, oy) = other.get_xy();
-                if (x - ox).powi(2) + (y - oy).powi(2) < TRUCK_CRASH_RADIUS.powi(2) {
-                    // oeuf
-                    tax += TAX_CRASH;
-                    collided_truck_idxes.push(idx);
-                    let hex = truck.get_hex();
-                    if self.cells.contains_key(&hex) {
-                        self.cells.insert(hex, Cell::Wreckage);
-                    }
-                    // the other piece of wreckage will be inserted by the other truck.
-                }
-            }
+            ),
+            14,
+            890.0,
+            20.0,
+            TextAlign::Right,
+        );
+        if let Some(notice) = &self.notice {
+            drawutils::text_color(globals, notice, 14, 890.0, 54.0, TextAlign::Right, RED);
         }
-        for idx in (0..self.trucks.len()).rev() {
-            if collided_truck_idxes.contains(&idx) {
-                self.trucks.remove(idx);
+
+        if self.selected_card.is_none() {
+            if let Some(truck) = hovered_truck(&self.sim.board, self.time.alpha()) {
+                let (mouse_x, mouse_y) = mouse_position();
+                truck.draw_stats(mouse_x + 16.0, mouse_y + 16.0, globals);
             }
         }
+    }
 
-        // Update prices and add markets
-        let mut building_count = 0;
-        for (_coord, cell) in self.cells.iter_mut() {
-            if let Cell::Market(m) = cell {
-                m.prices.timestep();
-                building_count += 1;
-            } else if let Cell::Factory(..) = cell {
-                building_count += 1;
+    /// Try to undo or redo, telling the player if it didn't work
+    fn undo_redo(&mut self, command: Command) {
+        let (edit, verb) = match command {
+            Command::Undo => (self.sim.history.next_undo(), "undo"),
+            _ => (self.sim.history.next_redo(), "redo"),
+        };
+        let notice = match edit {
+            None => Some(format!("Nothing to {}", verb)),
+            Some(edit)
+                if !self
+                    .undo_window
+                    .allows(edit.tick, self.sim.ticks, self.time.is_paused()) =>
+            {
+                Some(format!("Too late to {} that", verb))
             }
-        }
-        if building_count < 6 + (1 + frames_elapsed / 3600) * self.trucks.len() as u64
-            && frames_elapsed % 60 == 0
-            && rand::thread_rng().gen_bool(0.2)
-        {
-            self.add_building(frames_elapsed);
-        }
+            Some(_) => {
+                if self.sim.apply(command) {
+                    None
+                } else {
+                    Some(format!("Can't {} that", verb))
+                }
+            }
+        };
+        self.notice = notice;
+    }
 
-        (money, tax)
+    fn lose(&self) -> Transition {
+        // No continuing a game that's over
+        if let Err(err) = save::delete() {
+            eprintln!("Couldn't delete the old save: {}", err);
+        }
+        Transition::Swap(GameMode::Lose(ModeLose::new(
+            self.sim.player_info.highscore,
+            self.sim.recording.clone(),
+        )))
     }
+}
+
+/// The hex under the mouse
+fn hovered_hex() -> Coordinate {
+    let (mouse_x, mouse_y) = mouse_position();
+    Coordinate::from_pixel(
+        mouse_x - BOARD_ORIGIN_X,
+        mouse_y - BOARD_ORIGIN_Y,
+        Spacing::PointyTop(HEX_RADIUS),
+    )
+}
+
+/// The truck under the mouse, `alpha` of the way from the last tick to the next
+fn hovered_truck(board: &Board, alpha: f32) -> Option<&Truck> {
+    let (mouse_x, mouse_y) = mouse_position();
+    let x = (mouse_x - BOARD_ORIGIN_X) / HEX_WIDTH;
+    let y = (mouse_y - BOARD_ORIGIN_Y) / HEX_WIDTH;
+    board
+        .trucks
+        .iter()
+        .filter(|truck| truck.underground == 0)
+        .map(|truck| {
+            let (tx, ty) = truck.get_xy_between(alpha);
+            (truck, (tx - x).powi(2) + (ty - y).powi(2))
+        })
+        .filter(|&(_, dist2)| dist2 < TRUCK_GRAB_RADIUS.powi(2))
+        .min_by(|(_, a), (_, b)| a.total_cmp(b))
+        .map(|(truck, _)| truck)
+}
 
-    fn draw(&self, globals: &Globals) {
+impl Board {
+    /// Draw the board, `alpha` of the way from the last tick to the next.
+    pub fn draw(&self, alpha: f32, globals: &Globals) {
         use macroquad::prelude::*;
 
         // Cells that gotta be drawn *after* trucks
@@ -547,7 +506,9 @@ impl Board {
 
         for (&coord, cell) in self.cells.iter() {
             match cell {
-                Cell::Factory(_) | Cell::Market(_) => toppers.push((coord, cell)),
+                Cell::Factory(_) | Cell::Market(_) | Cell::Warehouse(_) => {
+                    toppers.push((coord, cell))
+                }
                 _ => cell.draw(coord, globals),
             }
         }
@@ -575,99 +536,42 @@ impl Board {
             );
         }
 
-        for truck in self.trucks.iter() {
-            truck.draw(globals);
-        }
-        for (coord, cell) in toppers {
-            cell.draw(coord, globals);
-        }
-    }
-
-    /// Check if the given hex can be driven through
-    fn is_passable(
-        cells: &HashMap<Coordinate, Cell>,
-        coord: Coordinate,
-        out_of_bounds_immunity: bool,
-    ) -> bool {
-        let target = cells.get(&coord);
-        match target {
-            None => out_of_bounds_immunity,
-            Some(Cell::Wreckage) => false,
-            _ => true,
-        }
-    }
-
-    /// Add a new market or factory, accounting for current buildings.
-    fn add_building(&mut self, frames_elapsed: u64) {
-        let mut rng = rand::thread_rng();
-
-        // Count the number of markets. If there are markets without a factory for them, add the factory
-        // This maps items to bitmaps. Bit 1 = factory, bit 2 = market.
-        let mut item_statuses = HashMap::<_, u8>::new();
-        for (_coord, cell) in self.cells.iter() {
-            match cell {
-                Cell::Factory(f) => {
-                    *item_statuses.entry(&f.product).or_default() |= 0b01;
-                }
-                Cell::Market(Market {
-                    request: ItemFilter::Specific(item),
-                    ..
-                }) => {
-                    *item_statuses.entry(&item).or_default() |= 0b10;
+        // Join up the ends of each tunnel, once per tunnel
+        for (&coord, cell) in self.cells.iter() {
+            if let Cell::Instruction(Instruction::Tunnel { to }) = cell {
+                if (coord.x, coord.y) < (to.x, to.y) {
+                    draw_tunnel_link(hex_center(coord), hex_center(*to));
                 }
-                _ => {}
             }
         }
 
-        let lacking_factory = item_statuses
-            .into_iter()
-            .filter(|(_item, bitmask)| *bitmask == 0b10)
-            .collect::<Vec<_>>();
-        let new_building = if lacking_factory.is_empty() {
-            // Nothing is lacking, make up something totally random
-            if rng.gen_bool(0.6) {
-                Cell::Factory(Factory::generate(Item::sample(), frames_elapsed))
-            } else {
-                Cell::Market(Market::generate(
-                    if rng.gen_bool(0.2) {
-                        ItemFilter::Any
-                    } else {
-                        ItemFilter::Specific(Item::sample())
-                    },
-                    frames_elapsed,
-                ))
-            }
-        } else {
-            Cell::Factory(Factory::generate(
-                lacking_factory[rng.gen_range(0..lacking_factory.len())]
-                    .0
-                    .to_owned(),
-                frames_elapsed,
-            ))
-        };
-
-        // Insert it somewhere, hopefully
-        let mut canidates = Coordinate::new(0, 0)
-            .ring_iter(self.radius as i32 + 1, Spin::CW(Direction::XY))
-            .collect::<Vec<_>>();
-        // shut
-        #[allow(clippy::map_entry)]
-        loop {
-            let end = match canidates.len() {
-                0 => break,
-                it => it,
-            };
-            let coord = canidates.remove(rng.gen_range(0..end));
-            if !self.cells.contains_key(&coord) {
-                self.cells.insert(coord, new_building);
-                break;
+        // Trucks in tunnels can't be seen,
+        // and trucks on bridges go over the top of the trucks underneath
+        let (upper, lower): (Vec<_>, Vec<_>) = self
+            .trucks
+            .iter()
+            .filter(|truck| truck.underground == 0)
+            .partition(|truck| self.truck_layer(truck) > 0);
+        for truck in lower {
+            truck.draw(alpha, globals);
+        }
+        for (&coord, cell) in self.cells.iter() {
+            if let Cell::Instruction(Instruction::Bridge { over }) = cell {
+                let (x, y) = hex_center(coord);
+                draw_bridge_deck(*over, x, y);
             }
         }
+        for truck in upper {
+            truck.draw(alpha, globals);
+        }
+        for (coord, cell) in toppers {
+            cell.draw(coord, globals);
+        }
     }
 }
 
 impl PlayerInfo {
-    fn draw(&self, frames_elapsed: u64, globals: &Globals) {
+    pub fn draw(&self, selected_card: Option<&SelectedCard>, ticks: u64, globals: &Globals) {
         use macroquad::prelude::*;
 
         draw_texture(
@@ -677,7 +581,7 @@ impl PlayerInfo {
             WHITE,
         );
         for (idx, card) in self.hand.iter().enumerate() {
-            match &self.selected_card {
+            match selected_card {
                 Some(sel) if sel.original_idx == Some(idx) => {
                     // skip this card
                 }
@@ -687,7 +591,7 @@ impl PlayerInfo {
                 }
             }
         }
-        if let Some(sel) = &self.selected_card {
+        if let Some(sel) = selected_card {
             let (cx, cy) = mouse_position();
             sel.draw(cx, cy, globals);
         }
@@ -711,7 +615,7 @@ impl PlayerInfo {
         );
         drawutils::text(
             globals,
-            &format!("Timer: {}", TAX_TIMER - frames_elapsed % TAX_TIMER),
+            &format!("Timer: {}", TAX_TIMER - ticks % TAX_TIMER),
             18,
             HUD_LEFT_POS,
             CARD_TOP_POS + 60.0,
@@ -0,0 +1,133 @@
+use crate::{
+    drawutils::{self, HEX_RADIUS},
+    sim::{cards::Dials, events::Event, forecast::Route, Board},
+    Globals,
+};
+
+use super::{
+    cards::SelectedCard,
+    forecast::{draw_conflict, hex_center, route_color},
+};
+
+use hex2d::Coordinate;
+
+/// How many hexes ahead the preview looks
+const PREVIEW_HEXES: usize = 8;
+
+/// What would happen if the selected card got dropped where it's hovering.
+pub struct PlacementPreview {
+    /// Where it was worked out for, so it's only redone when something changes
+    coord: Coordinate,
+    dials: Dials,
+    ticks: u64,
+
+    /// Routes as they are now, for just the trucks the card would change
+    before: Vec<Route>,
+    /// Routes with the card down, for just the trucks the card would change
+    after: Vec<Route>,
+    /// Crashes that would only happen with the card down
+    new_conflicts: Vec<Coordinate>,
+}
+
+impl PlacementPreview {
+    /// Work out what dropping `selected` at `coord` would do.
+    ///
+    /// Return `None` if it can't go there.
+    pub fn new(
+        board: &Board,
+        ticks: u64,
+        selected: &SelectedCard,
+        coord: Coordinate,
+    ) -> Option<Self> {
+        let after = board.forecast_with_card(
+            &selected.card,
+            coord,
+            selected.dials,
+            ticks,
+            PREVIEW_HEXES,
+        )?;
+        let before = board.forecast(ticks, PREVIEW_HEXES);
+
+        let mut changed_before = Vec::new();
+        let mut changed_after = Vec::new();
+        for route in after.routes {
+            match before.routes.iter().find(|it| it.truck == route.truck) {
+                Some(old) if Self::same_route(old, &route) => {}
+                old => {
+                    changed_before.extend(old.cloned());
+                    changed_after.push(route);
+                }
+            }
+        }
+        let new_conflicts = after
+            .conflicts
+            .into_iter()
+            .filter(|coord| !before.conflicts.contains(coord))
+            .collect();
+
+        Some(Self {
+            coord,
+            dials: selected.dials,
+            ticks,
+            before: changed_before,
+            after: changed_after,
+            new_conflicts,
+        })
+    }
+
+    /// Check if this preview is still good for the card at `coord` set up with `dials`, on tick `ticks`.
+    pub fn is_for(&self, coord: Coordinate, dials: Dials, ticks: u64) -> bool {
+        self.coord == coord && self.dials == dials && self.ticks == ticks
+    }
+
+    pub fn draw(&self, globals: &Globals) {
+        use macroquad::prelude::*;
+
+        // Where they'd have gone in gray, where they'll go now in color
+        for route in self.before.iter() {
+            route.draw(Color::new(0.3, 0.3, 0.3, 0.5), 3.0);
+        }
+        for route in self.after.iter() {
+            route.draw(route_color(route.truck), 5.0);
+        }
+        for &coord in self.new_conflicts.iter() {
+            draw_conflict(coord);
+        }
+
+        let mut warnings = Vec::new();
+        if !self.new_conflicts.is_empty() {
+            warnings.push("Trucks would crash!");
+        }
+        for route in self.after.iter() {
+            let warning = match route.fate {
+                Some(Event::DroveOffBoard { .. }) => "Drives a truck off the board!",
+                Some(Event::HitWreckage { .. }) => "Drives a truck into wreckage!",
+                _ => continue,
+            };
+            if !warnings.contains(&warning) {
+                warnings.push(warning);
+            }
+        }
+        if !warnings.is_empty() {
+            let (x, y) = hex_center(self.coord);
+            drawutils::center_text_color(
+                globals,
+                &warnings.join("\n"),
+                16,
+                x,
+                y - HEX_RADIUS * 1.5,
+                RED,
+            );
+        }
+    }
+
+    /// Check if a truck goes the same way (and ends the same way) in both routes
+    fn same_route(a: &Route, b: &Route) -> bool {
+        a.fate == b.fate
+            && a.hexes.len() == b.hexes.len()
+            && a.hexes
+                .iter()
+                .zip(b.hexes.iter())
+                .all(|((_, a), (_, b))| a == b)
+    }
+}
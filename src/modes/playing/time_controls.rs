@@ -0,0 +1,183 @@
+use crate::{
+    drawutils::{self, TextAlign},
+    modes::clock::TickClock,
+    Globals,
+};
+
+use macroquad::prelude::{
+    get_frame_time, is_key_down, is_key_pressed, is_mouse_button_pressed, mouse_position, KeyCode,
+    MouseButton,
+};
+
+/// Speeds you can pick, as multiples of normal speed
+const SPEEDS: [f32; 3] = [1.0, 2.0, 4.0];
+/// How much faster the game goes while holding Tab
+const FAST_FORWARD_SPEED: f32 = 4.0;
+
+const BAR_LEFT: f32 = 10.0;
+const BAR_TOP: f32 = 10.0;
+const BUTTON_WIDTH: f32 = 44.0;
+const BUTTON_HEIGHT: f32 = 26.0;
+const BUTTON_GAP: f32 = 4.0;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Button {
+    Pause,
+    Play,
+    Double,
+    Quadruple,
+    Step,
+}
+
+const BUTTONS: [(Button, &str); 5] = [
+    (Button::Pause, "||"),
+    (Button::Play, ">"),
+    (Button::Double, "2x"),
+    (Button::Quadruple, "4x"),
+    (Button::Step, ">|"),
+];
+
+/// Pause, speed and single-step controls for the game clock.
+///
+/// The player can still do everything else while paused.
+pub struct TimeControls {
+    clock: TickClock,
+    paused: bool,
+    /// Index into `SPEEDS`
+    speed_idx: usize,
+}
+
+impl TimeControls {
+    pub fn new() -> Self {
+        Self {
+            clock: TickClock::new(),
+            paused: false,
+            speed_idx: 0,
+        }
+    }
+
+    /// Handle the buttons and hotkeys, and return how many ticks to run this frame.
+    pub fn update(&mut self) -> u32 {
+        let mut step = false;
+        let mut press = |button| match button {
+            Button::Pause => self.paused = true,
+            Button::Play => self.set_speed(0),
+            Button::Double => self.set_speed(1),
+            Button::Quadruple => self.set_speed(2),
+            Button::Step => {
+                self.paused = true;
+                step = true;
+            }
+        };
+
+        if is_mouse_button_pressed(MouseButton::Left) {
+            let (mouse_x, mouse_y) = mouse_position();
+            if let Some(button) = Self::button_at(mouse_x, mouse_y) {
+                press(button);
+            }
+        }
+        if is_key_pressed(KeyCode::Key1) {
+            press(Button::Play);
+        } else if is_key_pressed(KeyCode::Key2) {
+            press(Button::Double);
+        } else if is_key_pressed(KeyCode::Key4) {
+            press(Button::Quadruple);
+        } else if is_key_pressed(KeyCode::Period) {
+            press(Button::Step);
+        } else if is_key_pressed(KeyCode::Space) {
+            // toggle
+            self.paused = !self.paused;
+        }
+
+        if self.paused {
+            step as u32
+        } else {
+            // Hold tab to fast-forward
+            let speed = if is_key_down(KeyCode::Tab) {
+                FAST_FORWARD_SPEED
+            } else {
+                SPEEDS[self.speed_idx]
+            };
+            self.clock.advance(get_frame_time(), speed)
+        }
+    }
+
+    /// Check if the given screen position is over the bar, so clicks there don't go anywhere else
+    pub fn contains(&self, x: f32, y: f32) -> bool {
+        Self::button_at(x, y).is_some()
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    /// How far between ticks to draw things
+    pub fn alpha(&self) -> f32 {
+        if self.paused {
+            1.0
+        } else {
+            self.clock.alpha()
+        }
+    }
+
+    pub fn draw(&self, globals: &Globals) {
+        use macroquad::prelude::*;
+
+        for (idx, (button, label)) in BUTTONS.iter().enumerate() {
+            let x = Self::button_left(idx);
+            let active = match button {
+                Button::Pause => self.paused,
+                Button::Play => !self.paused && self.speed_idx == 0,
+                Button::Double => !self.paused && self.speed_idx == 1,
+                Button::Quadruple => !self.paused && self.speed_idx == 2,
+                Button::Step => false,
+            };
+            let fill = if active {
+                Color::from_rgba(80, 120, 200, 255)
+            } else {
+                Color::from_rgba(200, 205, 215, 255)
+            };
+            draw_rectangle(x, BAR_TOP, BUTTON_WIDTH, BUTTON_HEIGHT, fill);
+            draw_rectangle_lines(x, BAR_TOP, BUTTON_WIDTH, BUTTON_HEIGHT, 2.0, DARKGRAY);
+            drawutils::center_text_color(
+                globals,
+                label,
+                16,
+                x + BUTTON_WIDTH / 2.0,
+                BAR_TOP + BUTTON_HEIGHT / 2.0,
+                if active { WHITE } else { BLACK },
+            );
+        }
+        drawutils::text(
+            globals,
+            "Space: pause  .: step  1/2/4: speed",
+            14,
+            BAR_LEFT,
+            BAR_TOP + BUTTON_HEIGHT + 18.0,
+            TextAlign::Left,
+        );
+    }
+
+    fn set_speed(&mut self, speed_idx: usize) {
+        self.paused = false;
+        self.speed_idx = speed_idx;
+    }
+
+    fn button_left(idx: usize) -> f32 {
+        BAR_LEFT + idx as f32 * (BUTTON_WIDTH + BUTTON_GAP)
+    }
+
+    fn button_at(x: f32, y: f32) -> Option<Button> {
+        if !(BAR_TOP..BAR_TOP + BUTTON_HEIGHT).contains(&y) {
+            return None;
+        }
+        BUTTONS
+            .iter()
+            .enumerate()
+            .find(|(idx, _)| {
+                let left = Self::button_left(*idx);
+                (left..left + BUTTON_WIDTH).contains(&x)
+            })
+            .map(|(_, (button, _))| *button)
+    }
+}
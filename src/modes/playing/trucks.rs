@@ -1,36 +1,19 @@
 use std::f32::consts::TAU;
 
-use hex2d::{Coordinate, Direction, Spacing};
-
-use super::{cells::Cell, economy::Item};
 use crate::{
-    drawutils::{BOARD_ORIGIN_X, BOARD_ORIGIN_Y, HEX_HEIGHT, HEX_RADIUS, HEX_WIDTH},
+    drawutils::{self, TextAlign, BOARD_ORIGIN_X, BOARD_ORIGIN_Y, HEX_WIDTH},
+    sim::trucks::{Truck, TruckClass},
     Globals,
 };
 
-/// A truck carrying an item around.
-#[derive(Debug)]
-pub struct Truck {
-    /// What it's holding
-    pub cargo: Option<Item>,
-    /// How far to move per tick.
-    pub speed: f32,
-    /// Where it's facing
-    pub facing: Direction,
-    /// The hex this truck is based on.
-    pub position: Coordinate,
-    /// The fraction this truck is to the next hexagon.
-    ///
-    ///-  `0.0` = directly on this one
-    /// - `1.0` = all the way on the other one
-    pub move_progress: f32,
-    /// If it's ok to be off the board right now
-    pub out_of_bounds_immunity: bool,
-}
+/// Size of the box `draw_stats` draws
+const STATS_WIDTH: f32 = 150.0;
+const STATS_HEIGHT: f32 = 120.0;
 
 impl Truck {
-    pub fn draw(&self, globals: &Globals) {
-        let (cx, cy) = self.get_xy();
+    /// Draw this on the board, `alpha` of the way from the last tick to now.
+    pub fn draw(&self, alpha: f32, globals: &Globals) {
+        let (cx, cy) = self.get_xy_between(alpha);
         let cx = cx * HEX_WIDTH + BOARD_ORIGIN_X;
         let cy = cy * HEX_WIDTH + BOARD_ORIGIN_Y;
         self.draw_absolute(cx, cy, globals);
@@ -43,8 +26,15 @@ impl Truck {
         let rotation = self.facing.to_radians_pointy::<f32>() - TAU / 4.0;
         let tx = cx - 32.0;
         let ty = cy - 32.0;
+        let textures = &globals.assets.textures;
+        let texture = match self.class {
+            TruckClass::Van => textures.van,
+            TruckClass::Truck => textures.truck,
+            TruckClass::Lorry => textures.lorry,
+            TruckClass::Fridge => textures.fridge,
+        };
         draw_texture_ex(
-            globals.assets.textures.truck,
+            texture,
             tx,
             ty,
             WHITE,
@@ -55,65 +45,47 @@ impl Truck {
             },
         );
 
-        if let Some(ref item) = self.cargo {
+        // Line the cargo up along the bed, shrinking it to fit if there's a lot
+        let count = self.cargo.len();
+        let size = if count > 1 { 24.0 } else { 40.0 };
+        let spacing = 48.0 / count.max(2) as f32;
+        let (dy, dx) = rotation.sin_cos();
+        for (idx, item) in self.cargo.iter().enumerate() {
+            let offset = (idx as f32 - (count - 1) as f32 / 2.0) * spacing;
             draw_texture_ex(
                 item.texture(globals),
-                cx - 20.0,
-                cy - 20.0,
+                cx - dx * offset - size / 2.0,
+                cy - dy * offset - size / 2.0,
                 WHITE,
                 DrawTextureParams {
-                    dest_size: Some(vec2(40.0, 40.0)),
+                    dest_size: Some(vec2(size, size)),
                     ..Default::default()
                 },
             );
         }
     }
+    /// Draw how this truck's been doing in a box with its corner at `x, y`.
+    pub fn draw_stats(&self, x: f32, y: f32, globals: &Globals) {
+        use macroquad::prelude::*;
 
-    /// Get the hex coordinate this truck's center is over
-    pub fn get_hex(&self) -> Coordinate {
-        let target = self.position + self.facing;
-        if self.move_progress >= 0.5 {
-            target
-        } else {
-            self.position
-        }
-    }
-
-    /// Get the xy coordinates of the truck.
-    ///
-    /// - `(0, 0)` is the origin.
-    /// - `(1, 0)` is the center of the hex one to the right.
-    /// - `(0, 1)` is down one hex-radius from the center.
-    pub fn get_xy(&self) -> (f32, f32) {
-        // +theta is clockwise in this weird world
-        let (dy, dx) = (self.facing.to_radians_pointy::<f32>() - TAU / 4.0).sin_cos();
-
-        let base = self
-            .position
-            .to_pixel(Spacing::PointyTop(3.0f32.sqrt().recip()));
-        let x = base.0 + dx * self.move_progress;
-        let y = base.1 + dy * self.move_progress;
-        (x, y)
-    }
+        // Keep it on screen
+        let x = x.min(screen_width() - STATS_WIDTH);
+        let y = y.min(screen_height() - STATS_HEIGHT);
+        draw_rectangle(x, y, STATS_WIDTH, STATS_HEIGHT, WHITE);
+        draw_rectangle_lines(x, y, STATS_WIDTH, STATS_HEIGHT, 2.0, DARKGRAY);
 
-    pub fn from_rot(position: Coordinate, cargo: Option<Item>, rotation: i32) -> Self {
-        Truck {
-            cargo,
-            facing: Direction::from_int(rotation),
-            move_progress: 0.0,
-            out_of_bounds_immunity: false,
-            position,
-            speed: 1.0 / 40.0,
-        }
+        let stats = &self.stats;
+        let text = format!(
+            "{} #{}\nDeliveries: {}\nRevenue: {}${}\nTax caused: +{}\nHexes driven: {}\nAge: {}s",
+            self.class.name(),
+            self.id.0,
+            stats.deliveries,
+            if stats.revenue < 0 { "-" } else { "" },
+            stats.revenue.unsigned_abs(),
+            stats.tax,
+            stats.hexes,
+            stats.age / 60,
+        );
+        drawutils::text(globals, &text, 16, x + 8.0, y + 20.0, TextAlign::Left);
     }
 }
-
-#[derive(Debug)]
-pub struct Treads {
-    /// Board-pixel coordinates of the center of this tread
-    pub pos: (f32, f32),
-    /// Direction the truck was facing
-    pub facing: Direction,
-    /// Frames left alive
-    pub lifetime: u64,
-}
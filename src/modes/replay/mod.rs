@@ -0,0 +1,147 @@
+use crate::{
+    drawutils::{self, TextAlign},
+    sim::replay::{Playback, Recording},
+    Globals, Transition,
+};
+
+use super::{clock::TickClock, playing::effects::Effects};
+
+/// How far the arrow keys skip, in ticks
+const SKIP_TICKS: u64 = 60 * 5;
+/// Playback speeds, as multiples of normal speed
+const SPEEDS: [u32; 4] = [1, 2, 4, 8];
+
+const BAR_LEFT: f32 = 50.0;
+const BAR_RIGHT: f32 = 850.0;
+const BAR_TOP: f32 = 20.0;
+const BAR_HEIGHT: f32 = 12.0;
+
+pub struct ModeReplay {
+    playback: Playback,
+    clock: TickClock,
+    effects: Effects,
+    paused: bool,
+    /// Index into `SPEEDS`
+    speed_idx: usize,
+}
+
+impl ModeReplay {
+    pub fn new(recording: Recording) -> Self {
+        Self {
+            playback: Playback::new(recording),
+            clock: TickClock::new(),
+            effects: Effects::new(),
+            paused: false,
+            speed_idx: 0,
+        }
+    }
+
+    pub fn update(&mut self, _globals: &mut Globals) -> Transition {
+        use macroquad::prelude::*;
+
+        if is_key_pressed(KeyCode::Escape) {
+            return Transition::Pop;
+        }
+
+        if is_key_pressed(KeyCode::Space) {
+            self.paused = !self.paused;
+        }
+        if is_key_pressed(KeyCode::Up) {
+            self.speed_idx = (self.speed_idx + 1).min(SPEEDS.len() - 1);
+        } else if is_key_pressed(KeyCode::Down) {
+            self.speed_idx = self.speed_idx.saturating_sub(1);
+        }
+
+        let now = self.playback.sim.ticks;
+        if is_key_pressed(KeyCode::Left) {
+            self.playback.seek(now.saturating_sub(SKIP_TICKS));
+        } else if is_key_pressed(KeyCode::Right) {
+            self.playback.seek(now + SKIP_TICKS);
+        }
+
+        // Drag along the bar to scrub
+        if is_mouse_button_down(MouseButton::Left) {
+            let (mouse_x, mouse_y) = mouse_position();
+            if (BAR_TOP - BAR_HEIGHT..=BAR_TOP + BAR_HEIGHT * 2.0).contains(&mouse_y) {
+                let frac = ((mouse_x - BAR_LEFT) / (BAR_RIGHT - BAR_LEFT)).clamp(0.0, 1.0);
+                let target = (frac * self.playback.recording.length as f32) as u64;
+                if target != now {
+                    self.playback.seek(target);
+                }
+            }
+        }
+
+        if !self.paused {
+            let speed = SPEEDS[self.speed_idx] as f32;
+            for _ in 0..self.clock.advance(get_frame_time(), speed) {
+                let events = self.playback.step();
+                self.effects.push_events(&events);
+            }
+        } else if is_key_pressed(KeyCode::Period) {
+            let events = self.playback.step();
+            self.effects.push_events(&events);
+        }
+        self.effects.update(get_frame_time());
+
+        Transition::None
+    }
+
+    pub fn draw(&self, globals: &Globals) {
+        use macroquad::prelude::*;
+
+        clear_background(Color::from_rgba(250, 252, 255, 255));
+
+        let sim = &self.playback.sim;
+        let alpha = if self.paused || self.playback.is_finished() {
+            1.0
+        } else {
+            self.clock.alpha()
+        };
+        sim.board.draw(alpha, globals);
+        self.effects.draw(globals);
+        sim.player_info.draw(None, sim.ticks, globals);
+
+        // Progress bar
+        let length = self.playback.recording.length.max(1);
+        let progress = sim.ticks as f32 / length as f32;
+        draw_rectangle(
+            BAR_LEFT,
+            BAR_TOP,
+            BAR_RIGHT - BAR_LEFT,
+            BAR_HEIGHT,
+            Color::from_rgba(200, 205, 215, 255),
+        );
+        draw_rectangle(
+            BAR_LEFT,
+            BAR_TOP,
+            (BAR_RIGHT - BAR_LEFT) * progress,
+            BAR_HEIGHT,
+            Color::from_rgba(80, 120, 200, 255),
+        );
+
+        let status = if self.paused {
+            String::from("Paused")
+        } else {
+            format!("{}x", SPEEDS[self.speed_idx])
+        };
+        drawutils::text(
+            globals,
+            &format!(
+                "REPLAY  Seed {}  -  {}  -  Tick {} / {}",
+                self.playback.recording.seed, status, sim.ticks, self.playback.recording.length
+            ),
+            16,
+            BAR_LEFT,
+            BAR_TOP + BAR_HEIGHT + 20.0,
+            TextAlign::Left,
+        );
+        drawutils::text(
+            globals,
+            "Space: pause  .: step  Left/Right: skip  Up/Down: speed  Esc: back",
+            14,
+            BAR_RIGHT,
+            BAR_TOP + BAR_HEIGHT + 20.0,
+            TextAlign::Right,
+        );
+    }
+}
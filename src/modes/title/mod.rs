@@ -0,0 +1,79 @@
+use crate::{
+    drawutils::{self, TextAlign},
+    sim::save,
+    GameMode, Globals, Transition,
+};
+
+use super::ModePlaying;
+
+/// Shown on startup when there's a saved game to go back to.
+pub struct ModeTitle {
+    /// Why the save couldn't be loaded, if it couldn't
+    error: Option<String>,
+}
+
+impl ModeTitle {
+    #[allow(clippy::new_without_default)]
+    pub fn new() -> Self {
+        ModeTitle { error: None }
+    }
+
+    pub fn update(&mut self, _globals: &mut Globals) -> Transition {
+        use macroquad::prelude::*;
+
+        if is_key_pressed(KeyCode::N) {
+            Transition::Swap(GameMode::Playing(ModePlaying::new(::rand::random())))
+        } else if self.error.is_none()
+            && (is_mouse_button_pressed(MouseButton::Left) || is_key_pressed(KeyCode::Enter))
+        {
+            match save::load() {
+                Ok(Some(sim)) => Transition::Swap(GameMode::Playing(ModePlaying::from_sim(sim))),
+                Ok(None) => Transition::Swap(GameMode::Playing(ModePlaying::new(::rand::random()))),
+                Err(err) => {
+                    self.error = Some(err.to_string());
+                    Transition::None
+                }
+            }
+        } else {
+            Transition::None
+        }
+    }
+
+    pub fn draw(&self, globals: &Globals) {
+        use macroquad::prelude::*;
+        clear_background(Color::from_rgba(250, 252, 255, 255));
+
+        drawutils::text(globals, "GRIDLOCK", 30, 450.0, 100.0, TextAlign::Center);
+        match &self.error {
+            None => {
+                drawutils::text(
+                    globals,
+                    "Click anywhere to continue your saved game",
+                    20,
+                    450.0,
+                    400.0,
+                    TextAlign::Center,
+                );
+            }
+            Some(err) => {
+                drawutils::text_color(
+                    globals,
+                    &format!("Couldn't load your saved game:\n{}", err),
+                    18,
+                    450.0,
+                    400.0,
+                    TextAlign::Center,
+                    RED,
+                );
+            }
+        }
+        drawutils::text(
+            globals,
+            "Press N to start a new game",
+            20,
+            450.0,
+            460.0,
+            TextAlign::Center,
+        );
+    }
+}
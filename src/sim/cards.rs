@@ -0,0 +1,324 @@
+use super::{
+    cells::Instruction,
+    economy::{Item, ItemFilter},
+    trucks::TruckClass,
+};
+
+use hex2d::{Angle, Coordinate, Direction};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+/// A card held in hand.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum Card {
+    Truck {
+        cargo: Option<Item>,
+        class: TruckClass,
+    },
+    Instruction(CardInstruction),
+    Cleanup,
+    /// Somewhere to keep items until they're wanted
+    Warehouse,
+}
+
+impl Card {
+    /// Make the starting hand of cards
+    pub fn starting_hand() -> Vec<Self> {
+        vec![
+            Card::Truck {
+                cargo: None,
+                class: TruckClass::Truck,
+            },
+            Card::Truck {
+                cargo: None,
+                class: TruckClass::Truck,
+            },
+            Card::Instruction(CardInstruction::Direct),
+            Card::Instruction(CardInstruction::Shunt),
+            Card::Cleanup,
+        ]
+    }
+
+    /// Generate a random card.
+    pub fn generate(rng: &mut impl Rng) -> Self {
+        if rng.gen_bool(0.5) {
+            // Make an instruction
+            Card::Instruction(CardInstruction::sample(rng))
+        } else if rng.gen_bool(0.3) {
+            Card::Cleanup
+        } else if rng.gen_bool(0.1) {
+            Card::Warehouse
+        } else {
+            // Trucc
+            let cargo = if rng.gen_bool(0.8) {
+                None
+            } else {
+                Some(Item::sample(rng))
+            };
+            let class = match rng.gen_range(0..9) {
+                0 | 1 => TruckClass::Van,
+                2 | 3 => TruckClass::Lorry,
+                4 => TruckClass::Fridge,
+                _ => TruckClass::Truck,
+            };
+            Card::Truck { cargo, class }
+        }
+    }
+
+    /// Get the cost of this card
+    pub fn cost(&self) -> u32 {
+        match self {
+            Card::Truck { cargo, class } => {
+                let class_cost = match class {
+                    TruckClass::Van => 40,
+                    TruckClass::Truck => 50,
+                    TruckClass::Lorry => 60,
+                    TruckClass::Fridge => 70,
+                };
+                class_cost + if cargo.is_some() { 20 } else { 0 }
+            }
+            Card::Instruction(CardInstruction::Tunnel | CardInstruction::Bridge) => 40,
+            Card::Instruction(
+                CardInstruction::Sort | CardInstruction::Switch | CardInstruction::Counter,
+            ) => 30,
+            Card::Instruction(_) => 20,
+            Card::Cleanup => 10,
+            Card::Warehouse => 60,
+        }
+    }
+}
+
+/// The turns a Rotate card can make, in the order scrolling goes through them.
+/// Going forward wouldn't do anything, so that's left out.
+pub const ROTATE_ANGLES: [Angle; 5] = [
+    Angle::Right,
+    Angle::RightBack,
+    Angle::Back,
+    Angle::LeftBack,
+    Angle::Left,
+];
+
+/// How the player has set a card up before playing it.
+///
+/// The scroll wheel turns `rotation`, shift+scroll turns `alt_rotation`
+/// and ctrl+scroll turns `setting`. Each card reads whichever ones it cares about.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Dials {
+    pub rotation: i32,
+    pub alt_rotation: i32,
+    pub setting: i32,
+    /// Where the other end goes, for cards that join two hexes up.
+    /// The player picks it with a second click.
+    pub link: Option<Coordinate>,
+}
+
+/// An instruction blueprint on a card
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum CardInstruction {
+    /// Go in *this* direction
+    Direct,
+    /// Rotate relative by *this* much
+    Rotate,
+    /// Shunt in *this* direction
+    Shunt,
+    /// Send trucks carrying *this* item *this* way and the rest *that* way
+    Sort,
+    /// Send trucks *this* way, then *that* way, (then maybe *the other* way,) then round again
+    Switch,
+    /// Hold trucks for *this* long, or until the way ahead is clear
+    Signal,
+    /// Let trucks through on *this* turn of the traffic light cycle
+    Light,
+    /// Speed trucks up
+    Boost,
+    /// Slow trucks down
+    Slow,
+    /// Dig a tunnel from *here* to *there*
+    Tunnel,
+    /// Carry traffic going *this* way over the top
+    Bridge,
+    /// Throw out cargo, maybe *recycling* it
+    Dump,
+    /// Send trucks *this* way, except every *Nth* one goes *that* way
+    Counter,
+}
+
+impl CardInstruction {
+    /// Sample a random CardInstruction
+    pub fn sample(rng: &mut impl Rng) -> Self {
+        let samples = [
+            CardInstruction::Direct,
+            CardInstruction::Direct,
+            CardInstruction::Direct,
+            CardInstruction::Rotate,
+            CardInstruction::Rotate,
+            CardInstruction::Shunt,
+            CardInstruction::Sort,
+            CardInstruction::Switch,
+            CardInstruction::Signal,
+            CardInstruction::Light,
+            CardInstruction::Boost,
+            CardInstruction::Slow,
+            CardInstruction::Tunnel,
+            CardInstruction::Bridge,
+            CardInstruction::Dump,
+            CardInstruction::Counter,
+        ];
+        samples[rng.gen_range(0..samples.len())].clone()
+    }
+
+    /// Turn this into an Instruction
+    pub fn to_instruction(&self, dials: Dials) -> Instruction {
+        match self {
+            CardInstruction::Direct => Instruction::Direct(Direction::from_int(dials.rotation)),
+            CardInstruction::Rotate => Instruction::Rotate(
+                ROTATE_ANGLES[dials.rotation.rem_euclid(ROTATE_ANGLES.len() as i32) as usize],
+            ),
+            CardInstruction::Shunt => Instruction::Shunt(Direction::from_int(dials.rotation)),
+            CardInstruction::Sort => Instruction::Sort {
+                filter: ItemFilter::Specific(Item::from_int(dials.setting)),
+                matched: Direction::from_int(dials.rotation),
+                unmatched: Direction::from_int(dials.alt_rotation),
+            },
+            CardInstruction::Switch => {
+                let mut directions = vec![
+                    Direction::from_int(dials.rotation),
+                    Direction::from_int(dials.alt_rotation),
+                ];
+                // The setting picks a third way, or 0 for none
+                let third = dials.setting.rem_euclid(7);
+                if third != 0 {
+                    directions.push(Direction::from_int(third - 1));
+                }
+                Instruction::Switch {
+                    directions,
+                    next: 0,
+                }
+            }
+            CardInstruction::Signal => Instruction::Signal {
+                // The setting is seconds to wait, or 0 to wait until it's clear
+                hold: match dials.setting.rem_euclid(6) {
+                    0 => None,
+                    secs => Some(secs as u32 * 60),
+                },
+            },
+            CardInstruction::Light => Instruction::Light {
+                green_first: dials.setting.rem_euclid(2) == 0,
+                green: true,
+            },
+            CardInstruction::Boost => Instruction::Boost,
+            CardInstruction::Slow => Instruction::Slow,
+            // This is just for show, `Board::play_card` digs both ends at once
+            CardInstruction::Tunnel => Instruction::Tunnel {
+                to: dials.link.unwrap_or_else(|| Coordinate::new(0, 0)),
+            },
+            CardInstruction::Bridge => Instruction::Bridge {
+                over: Direction::from_int(dials.rotation),
+            },
+            CardInstruction::Dump => Instruction::Dump {
+                recycle: dials.setting.rem_euclid(2) == 1,
+            },
+            CardInstruction::Counter => Instruction::Counter {
+                // Every 2nd truck up to every 9th
+                every: dials.setting.rem_euclid(8) as u32 + 2,
+                straight: Direction::from_int(dials.rotation),
+                redirect: Direction::from_int(dials.alt_rotation),
+                count: 0,
+            },
+        }
+    }
+
+    /// Turn an Instruction back into the blueprint and dials that made it
+    pub fn from_instruction(instr: &Instruction) -> (Self, Dials) {
+        let rotation = |rotation| Dials {
+            rotation,
+            ..Default::default()
+        };
+        match instr {
+            Instruction::Rotate(angle) => (
+                CardInstruction::Rotate,
+                rotation(
+                    ROTATE_ANGLES
+                        .iter()
+                        .position(|it| it == angle)
+                        .unwrap_or_default() as i32,
+                ),
+            ),
+            Instruction::Direct(dir) => (CardInstruction::Direct, rotation(dir.to_int())),
+            Instruction::Shunt(dir) => (CardInstruction::Shunt, rotation(dir.to_int())),
+            Instruction::Bridge { over } => (CardInstruction::Bridge, rotation(over.to_int())),
+            Instruction::Sort {
+                filter,
+                matched,
+                unmatched,
+            } => (
+                CardInstruction::Sort,
+                Dials {
+                    rotation: matched.to_int(),
+                    alt_rotation: unmatched.to_int(),
+                    setting: match filter {
+                        ItemFilter::Specific(item) => item.to_int(),
+                        ItemFilter::Any => 0,
+                    },
+                    link: None,
+                },
+            ),
+            Instruction::Switch { directions, .. } => {
+                let way = |idx: usize| directions.get(idx).map(|dir| dir.to_int());
+                (
+                    CardInstruction::Switch,
+                    Dials {
+                        rotation: way(0).unwrap_or_default(),
+                        alt_rotation: way(1).unwrap_or_default(),
+                        setting: way(2).map_or(0, |dir| dir + 1),
+                        link: None,
+                    },
+                )
+            }
+            Instruction::Signal { hold } => (
+                CardInstruction::Signal,
+                Dials {
+                    setting: hold.map_or(0, |ticks| ticks as i32 / 60),
+                    ..Default::default()
+                },
+            ),
+            Instruction::Counter {
+                every,
+                straight,
+                redirect,
+                ..
+            } => (
+                CardInstruction::Counter,
+                Dials {
+                    rotation: straight.to_int(),
+                    alt_rotation: redirect.to_int(),
+                    setting: *every as i32 - 2,
+                    link: None,
+                },
+            ),
+            Instruction::Dump { recycle } => (
+                CardInstruction::Dump,
+                Dials {
+                    setting: *recycle as i32,
+                    ..Default::default()
+                },
+            ),
+            Instruction::Boost => (CardInstruction::Boost, Dials::default()),
+            Instruction::Slow => (CardInstruction::Slow, Dials::default()),
+            Instruction::Tunnel { to } => (
+                CardInstruction::Tunnel,
+                Dials {
+                    link: Some(*to),
+                    ..Default::default()
+                },
+            ),
+            Instruction::Light { green_first, .. } => (
+                CardInstruction::Light,
+                Dials {
+                    setting: if *green_first { 0 } else { 1 },
+                    ..Default::default()
+                },
+            ),
+        }
+    }
+}
@@ -0,0 +1,66 @@
+use super::economy::{Factory, ItemFilter, Market, Warehouse};
+
+use hex2d::{Angle, Coordinate, Direction};
+use serde::{Deserialize, Serialize};
+
+/// A Cell is a spot on a board that trucks can drive on.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Cell {
+    Empty,
+    /// Oh no, two trucks collided here.
+    Wreckage,
+    Instruction(Instruction),
+    Factory(Factory),
+    Market(Market),
+    Warehouse(Warehouse),
+}
+
+/// Special instructions you can place on the board.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Instruction {
+    /// Rotate the truck relative to its current direction
+    Rotate(Angle),
+    /// Direct the truck to the given direction
+    Direct(Direction),
+    /// Move the truck in the direction by one square
+    Shunt(Direction),
+    /// Send trucks carrying something that matches `filter` towards `matched`,
+    /// and everything else (including empty trucks) towards `unmatched`.
+    Sort {
+        filter: ItemFilter,
+        matched: Direction,
+        unmatched: Direction,
+    },
+    /// Send each truck the next way in `directions`, going round and round.
+    Switch {
+        /// Two or three ways out
+        directions: Vec<Direction>,
+        /// Index of the way the next truck goes
+        next: usize,
+    },
+    /// Hold trucks in place for this many ticks,
+    /// or if it's `None`, until the hex in front of them is clear.
+    Signal { hold: Option<u32> },
+    /// A traffic light. Trucks wait on it while it's red.
+    /// Lights with different `green_first` take turns, so a pair of them can share a crossing.
+    Light { green_first: bool, green: bool },
+    /// Speed trucks up
+    Boost,
+    /// Slow trucks down
+    Slow,
+    /// One end of a tunnel. Trucks that drive in come out at `to`, the other end.
+    Tunnel { to: Coordinate },
+    /// Trucks going `over` (or back the other way) drive over the top of everyone else.
+    Bridge { over: Direction },
+    /// Throw out whatever trucks are carrying.
+    /// Recycling dumps pay a little for it, the others charge a little.
+    Dump { recycle: bool },
+    /// Send every `every`th truck `redirect`, and the rest `straight`
+    Counter {
+        every: u32,
+        straight: Direction,
+        redirect: Direction,
+        /// How many trucks have gone `straight` since the last one got redirected
+        count: u32,
+    },
+}
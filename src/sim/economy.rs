@@ -0,0 +1,207 @@
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+/// How many items a warehouse holds
+pub const WAREHOUSE_CAPACITY: usize = 8;
+
+/// Items that can be bought and sold
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum Item {
+    RedApple,
+    Orange,
+    YellowLemon,
+    GreenLettuce,
+    PurpleGrape,
+    BrownTater,
+}
+
+impl Item {
+    /// Every item, in order
+    pub const ALL: [Item; 6] = [
+        Item::RedApple,
+        Item::Orange,
+        Item::YellowLemon,
+        Item::GreenLettuce,
+        Item::PurpleGrape,
+        Item::BrownTater,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Item::RedApple => "Apple",
+            Item::Orange => "Orange",
+            Item::YellowLemon => "Lemon",
+            Item::GreenLettuce => "Lettuce",
+            Item::PurpleGrape => "Grape",
+            Item::BrownTater => "Potato",
+        }
+    }
+
+    /// Check if this goes off if it's left out of the fridge for too long
+    pub fn is_perishable(&self) -> bool {
+        matches!(self, Item::GreenLettuce | Item::PurpleGrape)
+    }
+
+    /// Sample a random Item
+    pub fn sample(rng: &mut impl Rng) -> Self {
+        Item::ALL[rng.gen_range(0..6)].clone()
+    }
+
+    /// Get an item by its place in `ALL`, wrapping around
+    pub fn from_int(idx: i32) -> Self {
+        Item::ALL[idx.rem_euclid(Item::ALL.len() as i32) as usize].clone()
+    }
+
+    /// Get this item's place in `ALL`
+    pub fn to_int(&self) -> i32 {
+        Item::ALL.iter().position(|it| it == self).unwrap() as i32
+    }
+}
+
+/// Indicates what a Market is interested in.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum ItemFilter {
+    /// Any item is OK
+    Any,
+    /// A specifically wanted item
+    Specific(Item),
+}
+
+impl ItemFilter {
+    /// Check if this matches the given item
+    pub fn matches(&self, checkee: &Item) -> bool {
+        match self {
+            ItemFilter::Any => true,
+            ItemFilter::Specific(other) => checkee == other,
+        }
+    }
+
+    /// Get a filter from a card's setting dial: `0` is anything, then each item in turn.
+    pub fn from_setting(setting: i32) -> Self {
+        match setting.rem_euclid(Item::ALL.len() as i32 + 1) {
+            0 => ItemFilter::Any,
+            idx => ItemFilter::Specific(Item::from_int(idx - 1)),
+        }
+    }
+
+    pub fn sample(rng: &mut impl Rng) -> Self {
+        if rng.gen_bool(0.2) {
+            ItemFilter::Any
+        } else {
+            ItemFilter::Specific(Item::sample(rng))
+        }
+    }
+}
+
+/// A Factory that produces items.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Factory {
+    /// The thing this factory produces
+    pub product: Item,
+    /// How many it has left
+    pub stock: usize,
+}
+
+impl Factory {
+    pub fn generate(product: Item, _frames_elapsed: u64, rng: &mut impl Rng) -> Self {
+        Self {
+            stock: rng.gen_range(1..5),
+            product,
+        }
+    }
+}
+
+/// A Warehouse the player puts down to hold onto items for later.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Warehouse {
+    /// What it'll take in
+    pub filter: ItemFilter,
+    /// What's in it, oldest first
+    pub items: Vec<Item>,
+    /// How many it can hold
+    pub capacity: usize,
+}
+
+impl Warehouse {
+    pub fn new(filter: ItemFilter) -> Self {
+        Self {
+            filter,
+            items: Vec::new(),
+            capacity: WAREHOUSE_CAPACITY,
+        }
+    }
+}
+
+/// A Market that consumes items.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Market {
+    /// What the market wants
+    pub request: ItemFilter,
+    /// How many more of the item it wants
+    pub demand: usize,
+    /// Price generator
+    pub prices: Pricer,
+}
+
+impl Market {
+    pub fn generate(request: ItemFilter, frames_elapsed: u64, rng: &mut impl Rng) -> Self {
+        let is_any = matches!(request, ItemFilter::Any);
+        Self {
+            request,
+            demand: rng.gen_range(2..10),
+            prices: Pricer::new(
+                (frames_elapsed as f32 / 1000.0).sqrt() / 800.0 * if is_any { 0.5 } else { 1.0 },
+                5.0 + rng.gen_range(
+                    (frames_elapsed as f32 / 10.0).sqrt()..(frames_elapsed as f32 / 5.0).sqrt(),
+                ) * if is_any { 0.2 } else { 1.0 },
+                rng,
+            ),
+        }
+    }
+}
+
+/// Price generator
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Pricer {
+    /// "time" step to sample our equation at
+    time: f32,
+    /// How much to step by each sample
+    dt: f32,
+    /// Price multiplier
+    multiplier: f32,
+    m: f32,
+    n: f32,
+    o: f32,
+}
+
+impl Pricer {
+    /// Create a new Pricer with the given multiplier and dt
+    pub fn new(dt: f32, multiplier: f32, rng: &mut impl Rng) -> Self {
+        let m = rng.gen_range(1.0..3.0);
+        let n = rng.gen_range(1.0..2.0);
+        let o = rng.gen_range(2.0..4.0);
+
+        Self {
+            time: 0.0,
+            dt,
+            multiplier,
+            m,
+            n,
+            o,
+        }
+    }
+
+    /// Sample the price at the current time.
+    pub fn sample(&self) -> u32 {
+        // (3 + sin(tm) - cos(tn) - sin(to))/6 for the base price
+        let base = 3.0 + (self.time * self.m).sin()
+            - (self.time * self.n).cos()
+            - (self.time * self.o).sin();
+        let out = base / 6.0 * self.multiplier;
+        out as u32 + 1
+    }
+    /// Advance the timer
+    pub fn timestep(&mut self) {
+        self.time += self.dt;
+    }
+}
@@ -0,0 +1,164 @@
+//! Things that happen on the board, as they happen.
+//!
+//! The board reports what it did each tick as a list of these,
+//! and money and tax are worked out from them.
+//! Anything else that wants to know what went on (effects, stats, sounds) can read the same list.
+
+use hex2d::Coordinate;
+
+use super::{
+    economy::Item, trucks::TruckId, TAX_BAD_SELL, TAX_COLLISION, TAX_CRASH, TAX_OVERLOAD,
+    TAX_SHORTSELL,
+};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Event {
+    /// A truck sold its cargo at the market at `coord`.
+    Delivered {
+        truck: TruckId,
+        coord: Coordinate,
+        item: Item,
+        price: u32,
+    },
+    /// A truck brought something the market at `coord` didn't want.
+    BadSell {
+        truck: TruckId,
+        coord: Coordinate,
+        item: Item,
+    },
+    /// A truck showed up empty to the market at `coord`.
+    ShortSell { truck: TruckId, coord: Coordinate },
+    /// A truck picked up an item from the factory at `coord`.
+    Loaded {
+        truck: TruckId,
+        coord: Coordinate,
+        item: Item,
+    },
+    /// A truck threw its cargo out at the dump at `coord`, and paid `fee` for it.
+    Dumped {
+        truck: TruckId,
+        coord: Coordinate,
+        item: Item,
+        fee: u32,
+    },
+    /// A truck threw its cargo out at the recycling dump at `coord`, and got `price` for it.
+    Recycled {
+        truck: TruckId,
+        coord: Coordinate,
+        item: Item,
+        price: u32,
+    },
+    /// A truck left its cargo in the warehouse at `coord`.
+    Stored {
+        truck: TruckId,
+        coord: Coordinate,
+        item: Item,
+    },
+    /// A truck took an item out of the warehouse at `coord`.
+    Retrieved {
+        truck: TruckId,
+        coord: Coordinate,
+        item: Item,
+    },
+    /// Perishable cargo went off on a truck around `coord`.
+    Spoiled {
+        truck: TruckId,
+        coord: Coordinate,
+        item: Item,
+    },
+    /// A truck picked up an item while it was already carrying `lost`, which got thrown out.
+    Overload {
+        truck: TruckId,
+        coord: Coordinate,
+        lost: Item,
+    },
+    /// Two trucks ran into each other around `coord`.
+    /// `truck` is the one that got wrecked.
+    Crash {
+        truck: TruckId,
+        other: TruckId,
+        coord: Coordinate,
+    },
+    /// A truck ran into wreckage at `coord`.
+    HitWreckage { truck: TruckId, coord: Coordinate },
+    /// A truck drove off the board, headed for `coord`.
+    DroveOffBoard { truck: TruckId, coord: Coordinate },
+    /// The factory at `coord` ran out of stock and went away.
+    FactoryDepleted { coord: Coordinate },
+    /// The market at `coord` got everything it wanted and went away.
+    MarketSatisfied { coord: Coordinate },
+    /// A new factory or market showed up at `coord`.
+    BuildingSpawned { coord: Coordinate },
+}
+
+impl Event {
+    /// How much money the player gets from this
+    pub fn money(&self) -> u32 {
+        match self {
+            Event::Delivered { price, .. } | Event::Recycled { price, .. } => *price,
+            _ => 0,
+        }
+    }
+
+    /// How much money the player pays for this
+    pub fn cost(&self) -> u32 {
+        match self {
+            Event::Dumped { fee, .. } => *fee,
+            _ => 0,
+        }
+    }
+
+    /// How much the tax goes up from this
+    pub fn tax(&self) -> u32 {
+        match self {
+            Event::BadSell { .. } => TAX_BAD_SELL,
+            Event::ShortSell { .. } => TAX_SHORTSELL,
+            Event::Overload { .. } => TAX_OVERLOAD,
+            Event::Crash { .. } => TAX_CRASH,
+            Event::HitWreckage { .. } | Event::DroveOffBoard { .. } => TAX_COLLISION,
+            _ => 0,
+        }
+    }
+
+    /// The truck this happened to, if any
+    pub fn truck(&self) -> Option<TruckId> {
+        match self {
+            Event::Delivered { truck, .. }
+            | Event::BadSell { truck, .. }
+            | Event::ShortSell { truck, .. }
+            | Event::Loaded { truck, .. }
+            | Event::Stored { truck, .. }
+            | Event::Retrieved { truck, .. }
+            | Event::Dumped { truck, .. }
+            | Event::Recycled { truck, .. }
+            | Event::Spoiled { truck, .. }
+            | Event::Overload { truck, .. }
+            | Event::Crash { truck, .. }
+            | Event::HitWreckage { truck, .. }
+            | Event::DroveOffBoard { truck, .. } => Some(*truck),
+            _ => None,
+        }
+    }
+
+    /// Where this happened
+    pub fn coord(&self) -> Coordinate {
+        match self {
+            Event::Delivered { coord, .. }
+            | Event::BadSell { coord, .. }
+            | Event::ShortSell { coord, .. }
+            | Event::Loaded { coord, .. }
+            | Event::Stored { coord, .. }
+            | Event::Retrieved { coord, .. }
+            | Event::Dumped { coord, .. }
+            | Event::Recycled { coord, .. }
+            | Event::Spoiled { coord, .. }
+            | Event::Overload { coord, .. }
+            | Event::Crash { coord, .. }
+            | Event::HitWreckage { coord, .. }
+            | Event::DroveOffBoard { coord, .. }
+            | Event::FactoryDepleted { coord }
+            | Event::MarketSatisfied { coord }
+            | Event::BuildingSpawned { coord } => *coord,
+        }
+    }
+}
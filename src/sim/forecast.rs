@@ -0,0 +1,129 @@
+//! Working out where the trucks are about to go, without touching the real game.
+
+use super::{
+    cards::{Card, Dials},
+    events::Event,
+    trucks::TruckId,
+    Board,
+};
+
+use hex2d::Coordinate;
+
+use std::collections::HashMap;
+
+/// Longest a forecast will run for, in ticks, in case the trucks are crawling
+const MAX_FORECAST_TICKS: u64 = 60 * 30;
+
+/// Where one truck is expected to go.
+#[derive(Debug, Clone)]
+pub struct Route {
+    pub truck: TruckId,
+    /// Hexes the truck drives through, starting with the one it's on now,
+    /// paired with how many ticks from now it gets there.
+    pub hexes: Vec<(u64, Coordinate)>,
+    /// What cuts the route short, if anything does.
+    /// This is a crash, a wreck or driving off the board.
+    pub fate: Option<Event>,
+}
+
+/// Where all the trucks are expected to go.
+#[derive(Debug, Clone)]
+pub struct Forecast {
+    pub routes: Vec<Route>,
+    /// Hexes where trucks are going to crash into each other
+    pub conflicts: Vec<Coordinate>,
+}
+
+impl Board {
+    /// Play the trucks forward on a copy of the board until each has gone `hexes` hexes
+    /// (or come to a bad end).
+    ///
+    /// `frames_elapsed` is the tick the board is about to update on.
+    /// Nothing random happens in here, so buildings don't spawn during the forecast.
+    pub fn forecast(&self, frames_elapsed: u64, hexes: usize) -> Forecast {
+        let mut board = self.clone();
+        board.treads.clear();
+
+        let mut routes = board
+            .trucks
+            .iter()
+            .map(|truck| Route {
+                truck: truck.id,
+                hexes: vec![(0, truck.get_hex())],
+                fate: None,
+            })
+            .collect::<Vec<_>>();
+        let route_idxes = routes
+            .iter()
+            .enumerate()
+            .map(|(idx, route)| (route.truck, idx))
+            .collect::<HashMap<_, _>>();
+        let done = |route: &Route| route.fate.is_some() || route.hexes.len() > hexes;
+
+        let mut conflicts = Vec::new();
+        let mut events = Vec::new();
+        for tick in 1..=MAX_FORECAST_TICKS {
+            if routes.iter().all(done) {
+                break;
+            }
+
+            events.clear();
+            board.step_trucks(frames_elapsed + tick - 1, &mut events);
+
+            // The board itself says who crashes, so queued up trucks aren't mistaken for a pile-up
+            for event in events.drain(..) {
+                if let Event::Crash { coord, .. } = event {
+                    conflicts.push(coord);
+                }
+                if matches!(
+                    event,
+                    Event::Crash { .. } | Event::HitWreckage { .. } | Event::DroveOffBoard { .. }
+                ) {
+                    if let Some(&idx) = event.truck().and_then(|id| route_idxes.get(&id)) {
+                        if !done(&routes[idx]) {
+                            routes[idx].fate = Some(event);
+                        }
+                    }
+                }
+            }
+
+            for truck in board.trucks.iter() {
+                let route = match route_idxes.get(&truck.id) {
+                    Some(&idx) => &mut routes[idx],
+                    None => continue,
+                };
+                if done(route) || truck.underground > 0 {
+                    continue;
+                }
+
+                let hex = truck.get_hex();
+                if route.hexes.last().map(|(_, last)| *last) != Some(hex) {
+                    route.hexes.push((tick, hex));
+                }
+            }
+        }
+
+        conflicts.sort_by_key(|coord| (coord.x, coord.y));
+        conflicts.dedup();
+        Forecast { routes, conflicts }
+    }
+
+    /// Forecast what would happen if `card` got played at `coord`.
+    ///
+    /// Return `None` if it can't go there.
+    pub fn forecast_with_card(
+        &self,
+        card: &Card,
+        coord: Coordinate,
+        dials: Dials,
+        frames_elapsed: u64,
+        hexes: usize,
+    ) -> Option<Forecast> {
+        let mut board = self.clone();
+        if board.play_card(card, coord, dials) {
+            Some(board.forecast(frames_elapsed, hexes))
+        } else {
+            None
+        }
+    }
+}
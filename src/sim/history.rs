@@ -0,0 +1,201 @@
+//! Undoing and redoing what the player did to the board with their cards.
+
+use super::{
+    cards::{Card, CardInstruction},
+    cells::Cell,
+    Simulation, HAND_SIZE,
+};
+
+use hex2d::Coordinate;
+use serde::{Deserialize, Serialize};
+
+/// Most edits remembered at once. Older ones can't be undone anymore.
+const MAX_HISTORY: usize = 50;
+
+/// One thing the player did to the board.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Edit {
+    /// Tick this was last done (or undone) on
+    pub tick: u64,
+    /// `(coord, before, after)` for every hex that changed
+    cells: Vec<(Coordinate, Cell, Cell)>,
+    /// The card played to do this and where in the hand it was, if one was
+    card: Option<(usize, Card)>,
+}
+
+/// Everything the player can undo or redo.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct History {
+    undo: Vec<Edit>,
+    redo: Vec<Edit>,
+    /// Where the lifted instruction came from, and what it was before it got lifted
+    lifted_from: Option<(Coordinate, Cell)>,
+}
+
+impl History {
+    /// The edit that would get undone next
+    pub fn next_undo(&self) -> Option<&Edit> {
+        self.undo.last()
+    }
+
+    /// The edit that would get redone next
+    pub fn next_redo(&self) -> Option<&Edit> {
+        self.redo.last()
+    }
+
+    fn push(&mut self, edit: Edit) {
+        self.undo.push(edit);
+        if self.undo.len() > MAX_HISTORY {
+            self.undo.remove(0);
+        }
+        self.redo.clear();
+    }
+
+    /// Forget everything that could be redone, because the player did something new.
+    pub(super) fn clear_redo(&mut self) {
+        self.redo.clear();
+    }
+}
+
+impl Simulation {
+    /// Remember that `card` from `hand_idx` was played, changing the hexes in `befores` from what they used to be.
+    pub(super) fn record_play(
+        &mut self,
+        befores: Vec<(Coordinate, Cell)>,
+        hand_idx: usize,
+        card: Card,
+    ) {
+        let cells = befores
+            .into_iter()
+            .filter_map(|(coord, before)| {
+                let after = self.board.cells.get(&coord)?.clone();
+                Some((coord, before, after))
+            })
+            .collect::<Vec<_>>();
+        if !cells.is_empty() {
+            self.history.push(Edit {
+                tick: self.ticks,
+                cells,
+                card: Some((hand_idx, card)),
+            });
+        }
+    }
+
+    /// Remember where an instruction got lifted from.
+    pub(super) fn record_pick_up(&mut self, coord: Coordinate, before: Cell) {
+        self.history.lifted_from = Some((coord, before));
+    }
+
+    /// Remember that the lifted instruction went down at `coord` (where `before` used to be),
+    /// or nowhere.
+    pub(super) fn record_put_down(&mut self, coord: Option<(Coordinate, Cell)>) {
+        let (from, original) = match self.history.lifted_from.take() {
+            Some(it) => it,
+            None => return,
+        };
+
+        let mut cells = vec![(from, original, Cell::Empty)];
+        if let Some((coord, before)) = coord {
+            if let Some(after) = self.board.cells.get(&coord) {
+                if coord == from {
+                    // Put back where it came from, maybe turned
+                    cells[0].2 = after.clone();
+                } else {
+                    cells.push((coord, before, after.clone()));
+                }
+            }
+        }
+        if cells.iter().all(|(_, before, after)| before == after) {
+            // Nothing actually changed
+            return;
+        }
+
+        self.history.push(Edit {
+            tick: self.ticks,
+            cells,
+            card: None,
+        });
+    }
+
+    /// Take back the last edit, giving back the card and money.
+    ///
+    /// Return `true` if it worked.
+    /// It won't if the board's changed under it (say a truck wrecked there) or there's no room in the hand.
+    pub(super) fn undo(&mut self) -> bool {
+        let edit = match self.history.undo.last() {
+            Some(it) => it,
+            None => return false,
+        };
+        if self.player_info.lifted.is_some()
+            || (edit.card.is_some() && self.player_info.hand.len() >= HAND_SIZE)
+            || !edit
+                .cells
+                .iter()
+                .all(|(coord, _, after)| self.cell_is(*coord, after))
+        {
+            return false;
+        }
+
+        let mut edit = self.history.undo.pop().unwrap();
+        for (coord, before, _) in edit.cells.iter().rev() {
+            self.board.cells.insert(*coord, before.clone());
+        }
+        if let Some((hand_idx, card)) = &mut edit.card {
+            self.player_info.money += card.cost();
+            *hand_idx = (*hand_idx).min(self.player_info.hand.len());
+            self.player_info.hand.insert(*hand_idx, card.clone());
+        }
+        edit.tick = self.ticks;
+        self.history.redo.push(edit);
+        true
+    }
+
+    /// Do the last undone edit again, spending the card and money again.
+    ///
+    /// Return `true` if it worked.
+    pub(super) fn redo(&mut self) -> bool {
+        let edit = match self.history.redo.last() {
+            Some(it) => it,
+            None => return false,
+        };
+        if self.player_info.lifted.is_some()
+            || !edit
+                .cells
+                .iter()
+                .all(|(coord, before, _)| self.cell_is(*coord, before))
+        {
+            return false;
+        }
+        if let Some((hand_idx, _)) = &edit.card {
+            match self.player_info.hand.get(*hand_idx) {
+                Some(card) if card.cost() <= self.player_info.money => {}
+                _ => return false,
+            }
+        }
+
+        let mut edit = self.history.redo.pop().unwrap();
+        for (coord, _, after) in edit.cells.iter() {
+            self.board.cells.insert(*coord, after.clone());
+        }
+        if let Some((hand_idx, _)) = &edit.card {
+            let card = self.player_info.hand.remove(*hand_idx);
+            self.player_info.money -= card.cost();
+        }
+        edit.tick = self.ticks;
+        self.history.undo.push(edit);
+        true
+    }
+
+    /// Check if the cell at `coord` is still what the player left there.
+    ///
+    /// Instructions that keep track of things as trucks go by (like which way a switch is pointing)
+    /// still count as the same.
+    fn cell_is(&self, coord: Coordinate, cell: &Cell) -> bool {
+        match (self.board.cells.get(&coord), cell) {
+            (Some(Cell::Instruction(here)), Cell::Instruction(there)) => {
+                CardInstruction::from_instruction(here) == CardInstruction::from_instruction(there)
+            }
+            (here, _) => here == Some(cell),
+        }
+    }
+}
@@ -0,0 +1,1724 @@
+//! The rules of the game, with no rendering or input attached.
+//!
+//! Everything in here can be stepped tick-by-tick without a window,
+//! so it works just as well in tests or on a server as under macroquad.
+
+pub mod cards;
+pub mod cells;
+pub mod economy;
+pub mod events;
+pub mod forecast;
+pub mod history;
+pub mod replay;
+pub mod save;
+pub mod trucks;
+
+use self::{
+    cards::{Card, CardInstruction, Dials},
+    cells::{Cell, Instruction},
+    economy::{Factory, Item, ItemFilter, Market, Warehouse},
+    events::Event,
+    history::History,
+    replay::Recording,
+    trucks::{Treads, Truck, TruckClass, TruckId, MAX_SPEED, MIN_SPEED, SHELF_LIFE, SPEED_CHANGE},
+};
+
+use hex2d::{Angle, Coordinate, Direction, Spin};
+use rand::{Rng, SeedableRng};
+use rand_pcg::Pcg64;
+use serde::{Deserialize, Serialize};
+
+use std::{
+    collections::{hash_map::Entry, BTreeMap, HashMap},
+    f32::consts::TAU,
+};
+
+/// Tax happens every this many ticks
+pub const TAX_TIMER: u64 = 60 * 20;
+/// Tax increase from colliding with a wreckage or going off the board
+pub const TAX_COLLISION: u32 = 5;
+/// Tax increase from two trucks crashing into each other
+pub const TAX_CRASH: u32 = 10;
+/// Tax increase from picking up a good when full
+pub const TAX_OVERLOAD: u32 = 1;
+/// Tax increase from bringing an empty truck to a market
+pub const TAX_SHORTSELL: u32 = 2;
+/// Tax increase from bringing the wrong thing to market
+pub const TAX_BAD_SELL: u32 = 5;
+/// Money it costs to get rid of an item at a dump
+pub const DUMP_FEE: u32 = 2;
+/// Money a recycling dump pays for an item
+pub const RECYCLE_PRICE: u32 = 1;
+/// Money it costs to put a truck lifted off the board back down
+pub const MOVE_TRUCK_FEE: u32 = 10;
+/// How much of a truck's card cost you get back for selling it, in percent
+pub const TRUCK_REFUND_PERCENT: u32 = 50;
+
+// Trucks move all the way in one go each tick before crashes get checked.
+// That's only safe while even the fastest truck can't skip right past the smallest one.
+const _: () = assert!(MAX_SPEED < TruckClass::Van.crash_radius() * 2.0);
+/// How many ticks it takes to go one hex underground
+const TUNNEL_TICKS_PER_HEX: u32 = 15;
+/// Extra room a truck leaves behind the one in front when safe following is on
+const FOLLOW_GAP: f32 = 0.05;
+/// How many ticks a traffic light stays one color
+const LIGHT_PERIOD: u64 = 60 * 3;
+
+/// Board-space distance between the center of the truck and where treads ought to be drawn
+const TREAD_OFFSET: f32 = 10.0 / 64.0;
+/// How long a tread lives for
+pub const TREAD_LIFETIME: u64 = 300;
+
+/// Max hand size
+pub const HAND_SIZE: usize = 5;
+
+/// The one source of randomness for a game.
+///
+/// Everything random has to come out of this so the same seed always plays out the same way.
+pub type GameRng = Pcg64;
+
+/// A whole game in progress.
+#[derive(Serialize, Deserialize)]
+pub struct Simulation {
+    pub board: Board,
+    pub player_info: PlayerInfo,
+    /// How many ticks have been simulated
+    pub ticks: u64,
+    /// Set once the player couldn't pay their taxes
+    pub game_over: bool,
+
+    /// What the RNG was seeded with
+    pub seed: u64,
+    rng: GameRng,
+
+    /// Everything the player has done so far
+    pub recording: Recording,
+    /// Board edits the player can take back
+    pub history: History,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Board {
+    /// Maps coordinates to cells.
+    /// Any empty cells are impassable and shouldn't be driven into.
+    pub cells: HashMap<Coordinate, Cell>,
+    /// All the trucks
+    pub trucks: Vec<Truck>,
+    /// All the treads.
+    /// TODO this might be a lot of things to keep track of?
+    pub treads: Vec<Treads>,
+    /// Radius of the board proper (not counting the buildings on the outsides)
+    pub radius: usize,
+    /// ID the next truck put on the board gets
+    next_truck_id: u64,
+    /// If trucks wait behind the truck in front instead of running into the back of it
+    pub safe_following: bool,
+    /// Trucks that have left the board for good, as they were when they went
+    pub retired: Vec<Truck>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct PlayerInfo {
+    /// How much money I have
+    pub money: u32,
+    /// Current tax rate
+    pub tax: u32,
+    /// The most amount of money I've ever had
+    pub highscore: u32,
+
+    /// The cards the player has in hand
+    pub hand: Vec<Card>,
+    /// An instruction picked up off the board that hasn't been put down yet
+    pub lifted: Option<CardInstruction>,
+    /// A truck picked up off the board that hasn't been put down yet
+    pub lifted_truck: Option<Truck>,
+}
+
+/// Something the player does to the game.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum Command {
+    /// Play the card at `hand_idx` onto the board at `coord`.
+    PlayCard {
+        hand_idx: usize,
+        coord: Coordinate,
+        dials: Dials,
+    },
+    /// Throw away the card at `hand_idx`.
+    Discard { hand_idx: usize },
+    /// Lift the instruction at `coord` off the board.
+    PickUp { coord: Coordinate },
+    /// Put the lifted instruction down at `coord`, or throw it away if there's nowhere to put it.
+    PutDown {
+        coord: Option<Coordinate>,
+        dials: Dials,
+    },
+    /// Lift the truck `truck` off the board.
+    LiftTruck { truck: TruckId },
+    /// Put the lifted truck down at `coord` facing `rotation` for a fee,
+    /// or sell it back if there's nowhere to put it.
+    ///
+    /// If it's going on the board but the fee can't be paid, it stays lifted.
+    PutDownTruck {
+        coord: Option<Coordinate>,
+        rotation: i32,
+    },
+    /// Turn safe following on or off.
+    ToggleSafeFollowing,
+    /// Pay taxes early in exchange for a new card.
+    ForceTax,
+    /// Take back the last card played on the board, or the last instruction moved.
+    Undo,
+    /// Do the last undone thing again.
+    Redo,
+}
+
+impl Simulation {
+    /// Start a new game. The same seed always makes the same game.
+    pub fn new(seed: u64) -> Self {
+        let mut rng = GameRng::seed_from_u64(seed);
+        let player_info = PlayerInfo {
+            hand: Card::starting_hand(),
+            highscore: 0,
+            money: 100,
+            tax: 0,
+            lifted: None,
+            lifted_truck: None,
+        };
+
+        Self {
+            board: Board::new(5, &mut rng),
+            player_info,
+            ticks: 0,
+            game_over: false,
+            seed,
+            rng,
+            recording: Recording::new(seed),
+            history: History::default(),
+        }
+    }
+
+    /// Advance the game by one tick.
+    ///
+    /// Return everything that happened on the board.
+    pub fn tick(&mut self) -> Vec<Event> {
+        if self.game_over {
+            return Vec::new();
+        }
+
+        let events = self.board.update(self.ticks, &mut self.rng);
+        for event in events.iter() {
+            self.player_info.money += event.money();
+            self.player_info.money = self.player_info.money.saturating_sub(event.cost());
+            self.player_info.tax += event.tax();
+            self.player_info.highscore += event.money();
+        }
+
+        if self.ticks.is_multiple_of(TAX_TIMER) && self.ticks != 0 && self.apply_tax() {
+            self.game_over = true;
+        }
+
+        self.ticks += 1;
+        self.recording.length = self.ticks;
+        events
+    }
+
+    /// Do what the player asked.
+    ///
+    /// Return `true` if it actually did something.
+    /// Only those commands get recorded.
+    pub fn apply(&mut self, command: Command) -> bool {
+        if self.game_over {
+            return false;
+        }
+
+        let accepted = self.execute(command.clone());
+        if accepted {
+            if !matches!(command, Command::Undo | Command::Redo) {
+                // doing something new means the old future is gone
+                self.history.clear_redo();
+            }
+            self.recording.commands.push((self.ticks, command));
+        }
+        accepted
+    }
+
+    fn execute(&mut self, command: Command) -> bool {
+        match command {
+            Command::PlayCard {
+                hand_idx,
+                coord,
+                dials,
+            } => {
+                let card = match self.player_info.hand.get(hand_idx) {
+                    Some(it) => it,
+                    None => return false,
+                };
+                let befores = std::iter::once(coord)
+                    .chain(self.board.linked_hex(card, coord, dials))
+                    .filter_map(|coord| Some((coord, self.board.cells.get(&coord)?.clone())))
+                    .collect::<Vec<_>>();
+                if card.cost() > self.player_info.money || !self.board.play_card(card, coord, dials)
+                {
+                    return false;
+                }
+
+                let card = self.player_info.hand.remove(hand_idx);
+                self.player_info.money -= card.cost();
+                // Trucks drive off, so there's no taking them back
+                if !matches!(card, Card::Truck { .. }) {
+                    self.record_play(befores, hand_idx, card);
+                }
+                true
+            }
+            Command::Discard { hand_idx } => {
+                if hand_idx < self.player_info.hand.len() {
+                    self.player_info.hand.remove(hand_idx);
+                    true
+                } else {
+                    false
+                }
+            }
+            Command::PickUp { coord } => {
+                if self.player_info.lifted.is_some() {
+                    return false;
+                }
+                match self.board.cells.get(&coord) {
+                    // Tunnels are dug in, they only come out with a cleanup
+                    Some(Cell::Instruction(instr))
+                        if !matches!(instr, Instruction::Tunnel { .. }) =>
+                    {
+                        let (card_instr, _) = CardInstruction::from_instruction(instr);
+                        self.player_info.lifted = Some(card_instr);
+                        if let Some(before) = self.board.cells.insert(coord, Cell::Empty) {
+                            self.record_pick_up(coord, before);
+                        }
+                        true
+                    }
+                    _ => false,
+                }
+            }
+            Command::PutDown { coord, dials } => {
+                let card_instr = match self.player_info.lifted.take() {
+                    Some(it) => it,
+                    None => return false,
+                };
+                // If it can't go down here, it's lost
+                let placed = coord.and_then(|coord| {
+                    let before = self.board.cells.get(&coord).cloned()?;
+                    self.board
+                        .play_card(&Card::Instruction(card_instr), coord, dials)
+                        .then_some((coord, before))
+                });
+                self.record_put_down(placed);
+                true
+            }
+            Command::LiftTruck { truck } => {
+                if self.player_info.lifted_truck.is_some() {
+                    return false;
+                }
+                match self.board.lift_truck(truck) {
+                    Some(truck) => {
+                        self.player_info.lifted_truck = Some(truck);
+                        true
+                    }
+                    None => false,
+                }
+            }
+            Command::PutDownTruck { coord, rotation } => {
+                let coord = coord.filter(|&coord| self.board.is_on_board(coord));
+                if coord.is_some() && self.player_info.money < MOVE_TRUCK_FEE {
+                    return false;
+                }
+                let mut truck = match self.player_info.lifted_truck.take() {
+                    Some(it) => it,
+                    None => return false,
+                };
+                match coord {
+                    Some(coord) => {
+                        self.player_info.money -= MOVE_TRUCK_FEE;
+                        truck.position = coord;
+                        truck.facing = Direction::from_int(rotation);
+                        self.board.trucks.push(truck);
+                    }
+                    None => {
+                        // Nowhere to put it, so sell it off (cargo and all)
+                        self.player_info.money += truck.refund();
+                        self.board.retired.push(truck);
+                    }
+                }
+                true
+            }
+            Command::ToggleSafeFollowing => {
+                self.board.safe_following = !self.board.safe_following;
+                true
+            }
+            Command::ForceTax => {
+                if self.apply_tax() {
+                    self.game_over = true;
+                }
+                true
+            }
+            Command::Undo => self.undo(),
+            Command::Redo => self.redo(),
+        }
+    }
+
+    // return `true` to quit
+    fn apply_tax(&mut self) -> bool {
+        match self.player_info.money.checked_sub(self.player_info.tax) {
+            Some(it) => self.player_info.money = it,
+            None => {
+                // oh no we ran out of money :(
+                return true;
+            }
+        }
+
+        if self.player_info.hand.len() < HAND_SIZE {
+            self.player_info.hand.push(Card::generate(&mut self.rng));
+        }
+        self.player_info.tax += 1;
+        false
+    }
+}
+
+impl Board {
+    /// Make a new board with the given radius and a few starting buildings.
+    pub fn new(radius: usize, rng: &mut GameRng) -> Self {
+        let mut board = Board {
+            cells: {
+                let mut map = HashMap::new();
+                for coord in Coordinate::new(0, 0).range_iter(radius as i32) {
+                    map.insert(coord, Cell::Empty);
+                }
+                map
+            },
+            trucks: vec![],
+            treads: vec![],
+            radius,
+            next_truck_id: 0,
+            safe_following: false,
+            retired: vec![],
+        };
+
+        // Generate stuff
+        for idx in 0..6 {
+            board.add_building((idx + 1) * 250, rng);
+        }
+
+        board
+    }
+
+    /// Put a truck on the board, giving it a fresh ID.
+    pub fn add_truck(&mut self, mut truck: Truck) -> TruckId {
+        let id = TruckId(self.next_truck_id);
+        self.next_truck_id += 1;
+        truck.id = id;
+        self.trucks.push(truck);
+        id
+    }
+
+    /// Take the truck `id` off the board, ready to be put down somewhere else.
+    ///
+    /// Trucks in tunnels can't be got at.
+    pub fn lift_truck(&mut self, id: TruckId) -> Option<Truck> {
+        let idx = self
+            .trucks
+            .iter()
+            .position(|truck| truck.id == id && truck.underground == 0)?;
+        let mut truck = self.trucks.remove(idx);
+        truck.move_progress = 0.0;
+        truck.out_of_bounds_immunity = false;
+        truck.waited = 0;
+        truck.last_xy = None;
+        Some(truck)
+    }
+
+    /// Put what's on the card down at `coord`, if it can go there.
+    ///
+    /// Return `true` if it did.
+    pub fn play_card(&mut self, card: &Card, coord: Coordinate, dials: Dials) -> bool {
+        if !self.is_on_board(coord) {
+            return false;
+        }
+
+        match card {
+            Card::Instruction(CardInstruction::Tunnel) => {
+                let exit = match dials.link {
+                    Some(it) => it,
+                    None => return false,
+                };
+                if exit == coord
+                    || !self.can_hold_instruction(coord)
+                    || !self.can_hold_instruction(exit)
+                {
+                    return false;
+                }
+                self.cells
+                    .insert(coord, Cell::Instruction(Instruction::Tunnel { to: exit }));
+                self.cells
+                    .insert(exit, Cell::Instruction(Instruction::Tunnel { to: coord }));
+            }
+            Card::Instruction(card_instr) => {
+                if !self.can_hold_instruction(coord) {
+                    return false;
+                }
+                let instr = card_instr.to_instruction(dials);
+                self.cells.insert(coord, Cell::Instruction(instr));
+            }
+            Card::Truck { cargo, class } => {
+                self.add_truck(Truck::from_rot(
+                    coord,
+                    *class,
+                    cargo.clone(),
+                    dials.rotation,
+                ));
+            }
+            Card::Warehouse => {
+                if !matches!(self.cells.get(&coord), Some(Cell::Empty)) {
+                    return false;
+                }
+                let filter = ItemFilter::from_setting(dials.setting);
+                self.cells
+                    .insert(coord, Cell::Warehouse(Warehouse::new(filter)));
+            }
+            Card::Cleanup => {
+                // Counters partway through a count just start over,
+                // and only get cleared off once they're back at zero
+                if let Some(Cell::Instruction(Instruction::Counter { count, .. })) =
+                    self.cells.get_mut(&coord)
+                {
+                    if *count > 0 {
+                        *count = 0;
+                        return true;
+                    }
+                }
+                // Tunnels get filled in at both ends
+                if let Some(to) = self.linked_hex(card, coord, dials) {
+                    self.cells.insert(to, Cell::Empty);
+                }
+                self.cells.insert(coord, Cell::Empty);
+            }
+        }
+        true
+    }
+
+    /// Check if an instruction can go on the given hex.
+    ///
+    /// Tunnels can't be built over, or they'd be left with one end.
+    pub fn can_hold_instruction(&self, coord: Coordinate) -> bool {
+        self.is_on_board(coord)
+            && match self.cells.get(&coord) {
+                Some(Cell::Empty) => true,
+                Some(Cell::Instruction(instr)) => !matches!(instr, Instruction::Tunnel { .. }),
+                _ => false,
+            }
+    }
+
+    /// The other hex playing `card` at `coord` would change, for cards that change two
+    fn linked_hex(&self, card: &Card, coord: Coordinate, dials: Dials) -> Option<Coordinate> {
+        match card {
+            Card::Instruction(CardInstruction::Tunnel) => dials.link,
+            Card::Cleanup => match self.cells.get(&coord) {
+                Some(Cell::Instruction(Instruction::Tunnel { to })) => Some(*to),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    /// Update the board and trucks.
+    ///
+    /// Return everything that happened, in the order it happened.
+    pub fn update(&mut self, frames_elapsed: u64, rng: &mut GameRng) -> Vec<Event> {
+        let mut events = Vec::new();
+
+        self.step_trucks(frames_elapsed, &mut events);
+
+        // Update prices and add markets
+        let mut building_count = 0;
+        for (_coord, cell) in self.cells.iter_mut() {
+            if let Cell::Market(m) = cell {
+                m.prices.timestep();
+                building_count += 1;
+            } else if let Cell::Factory(..) = cell {
+                building_count += 1;
+            }
+        }
+        if building_count < 6 + (1 + frames_elapsed / 3600) * self.trucks.len() as u64
+            && frames_elapsed.is_multiple_of(60)
+            && rng.gen_bool(0.2)
+        {
+            if let Some(coord) = self.add_building(frames_elapsed, rng) {
+                events.push(Event::BuildingSpawned { coord });
+            }
+        }
+
+        events
+    }
+
+    /// Move the trucks along, see what they run into, and age the treads.
+    ///
+    /// This part of the update never touches the RNG.
+    fn step_trucks(&mut self, frames_elapsed: u64, events: &mut Vec<Event>) {
+        let first_event = events.len();
+        let first_retired = self.retired.len();
+        // Trucks always go oldest first, even after being lifted and put back down
+        self.trucks.sort_by_key(|truck| truck.id);
+
+        // Change the lights
+        let first_turn = (frames_elapsed / LIGHT_PERIOD).is_multiple_of(2);
+        for cell in self.cells.values_mut() {
+            if let Cell::Instruction(Instruction::Light { green_first, green }) = cell {
+                *green = *green_first == first_turn;
+            }
+        }
+
+        // Bring trucks up out of tunnels
+        let cells = &self.cells;
+        let retired = &mut self.retired;
+        self.trucks.retain_mut(|truck| {
+            if truck.underground == 0 {
+                return true;
+            }
+            truck.underground -= 1;
+            if truck.underground > 0 || Board::is_passable(cells, truck.position, false) {
+                return true;
+            }
+            // Something's blocking the way out
+            events.push(Board::collision_event(cells, truck.id, truck.position));
+            retired.push(truck.clone());
+            false
+        });
+
+        let held = self.held_trucks();
+        for (truck, &is_held) in self.trucks.iter_mut().zip(held.iter()) {
+            truck.last_xy = Some(truck.get_xy());
+            truck.stats.age += 1;
+            if is_held {
+                // Stand still
+                truck.waited += 1;
+            } else {
+                truck.waited = 0;
+            }
+        }
+
+        // Update all my truccs and remove the collided ones
+        let cells = &mut self.cells;
+        let retired = &mut self.retired;
+        let mut truck_idx = 0;
+        self.trucks.retain_mut(|truck| {
+            let is_held = held[truck_idx];
+            truck_idx += 1;
+            let kept = is_held || truck.underground > 0 || Board::move_truck(cells, truck, events);
+            if !kept {
+                retired.push(truck.clone());
+            }
+            kept
+        });
+        self.crash_trucks(events);
+
+        // Perishables go off if they're out of the fridge too long
+        for truck in self.trucks.iter_mut() {
+            if !truck.cargo.iter().any(Item::is_perishable) {
+                truck.spoiling = 0;
+                continue;
+            }
+            if truck.class == TruckClass::Fridge {
+                continue;
+            }
+            truck.spoiling += 1;
+            if truck.spoiling >= SHELF_LIFE {
+                truck.spoiling = 0;
+                let coord = truck.get_hex();
+                let id = truck.id;
+                truck.cargo.retain(|item| {
+                    if item.is_perishable() {
+                        events.push(Event::Spoiled {
+                            truck: id,
+                            coord,
+                            item: item.clone(),
+                        });
+                    }
+                    !item.is_perishable()
+                });
+            }
+        }
+
+        // Keep score for each truck, including the ones that just came off the board
+        for event in events[first_event..].iter() {
+            let truck = event.truck().and_then(|id| {
+                self.trucks
+                    .iter_mut()
+                    .chain(self.retired[first_retired..].iter_mut())
+                    .find(|truck| truck.id == id)
+            });
+            if let Some(truck) = truck {
+                truck.stats.record(event);
+            }
+        }
+
+        // Add treads
+        if frames_elapsed.is_multiple_of(3) {
+            for truck in self
+                .trucks
+                .iter()
+                .filter(|truck| truck.waited == 0 && truck.underground == 0)
+            {
+                let truck_pos = truck.get_xy();
+                let (dy, dx) = (truck.facing.to_radians_pointy::<f32>() - TAU / 4.0).sin_cos();
+                let pos = (
+                    truck_pos.0 + dx * TREAD_OFFSET,
+                    truck_pos.1 + dy * TREAD_OFFSET,
+                );
+                self.treads.push(Treads {
+                    pos,
+                    facing: truck.facing,
+                    lifetime: TREAD_LIFETIME,
+                });
+            }
+        }
+
+        // Update treads
+        self.treads.retain_mut(|tread| {
+            if tread.lifetime == 0 {
+                // drop it
+                false
+            } else {
+                tread.lifetime -= 1;
+                true
+            }
+        });
+    }
+
+    /// Move one truck towards the next hex, and do whatever it finds there.
+    ///
+    /// Return `false` if it got wrecked.
+    fn move_truck(
+        cells: &mut HashMap<Coordinate, Cell>,
+        truck: &mut Truck,
+        events: &mut Vec<Event>,
+    ) -> bool {
+        truck.move_progress += truck.speed;
+
+        // Where the truck is going
+        let target = truck.position + truck.facing;
+        // Where the center of the truck is
+        let realpos = truck.get_hex();
+
+        if truck.move_progress > 0.5 {
+            // we crossed a hex, no longer immune
+            truck.out_of_bounds_immunity = false;
+        }
+
+        if !Board::is_passable(cells, realpos, truck.out_of_bounds_immunity) {
+            events.push(Board::collision_event(cells, truck.id, realpos));
+            // oh no, clobber this position into wreckage
+            match cells.get(&truck.position) {
+                Some(Cell::Factory(..)) | Some(Cell::Market(..)) => {
+                    cells.remove(&truck.position);
+                }
+                Some(..) => {
+                    cells.insert(truck.position, Cell::Wreckage);
+                }
+                _ => {}
+            }
+
+            // delt trucc
+            return false;
+        }
+
+        let arrived = truck.move_progress >= 1.0;
+        let mut tunnel_to = None;
+        if arrived {
+            // We're on the center of the next coord
+            truck.position = target;
+            truck.move_progress = 0.0;
+            truck.stats.hexes += 1;
+
+            // Take a special action?
+            let coord = truck.position;
+            let cell = cells.entry(coord);
+            if let Entry::Occupied(mut occupied) = cell {
+                match occupied.get_mut() {
+                    Cell::Factory(factory) => {
+                        if truck.is_full() && !truck.cargo.is_empty() {
+                            // uh-oh, the oldest thing falls off to make room
+                            let lost = truck.cargo.remove(0);
+                            events.push(Event::Overload {
+                                truck: truck.id,
+                                coord,
+                                lost,
+                            });
+                        }
+                        // Fill 'er up
+                        loop {
+                            truck.cargo.push(factory.product.clone());
+                            events.push(Event::Loaded {
+                                truck: truck.id,
+                                coord,
+                                item: factory.product.clone(),
+                            });
+                            factory.stock -= 1;
+                            if factory.stock == 0 || truck.is_full() {
+                                break;
+                            }
+                        }
+                        if factory.stock == 0 {
+                            // clear the factory
+                            occupied.remove();
+                            events.push(Event::FactoryDepleted { coord });
+                            // and it's ok to drive over empty for now
+                            truck.out_of_bounds_immunity = true;
+                        }
+                        truck.facing = truck.facing + Angle::Back;
+                    }
+                    Cell::Market(market) => {
+                        if truck.cargo.is_empty() {
+                            // uh-oh
+                            events.push(Event::ShortSell {
+                                truck: truck.id,
+                                coord,
+                            });
+                        } else if !truck.cargo.iter().any(|item| market.request.matches(item)) {
+                            // oh no, they take the oldest thing off you anyways
+                            events.push(Event::BadSell {
+                                truck: truck.id,
+                                coord,
+                                item: truck.cargo.remove(0),
+                            });
+                        } else {
+                            // noice, sell everything they want and keep the rest
+                            let mut idx = 0;
+                            while idx < truck.cargo.len() && market.demand > 0 {
+                                if market.request.matches(&truck.cargo[idx]) {
+                                    events.push(Event::Delivered {
+                                        truck: truck.id,
+                                        coord,
+                                        item: truck.cargo.remove(idx),
+                                        price: market.prices.sample(),
+                                    });
+                                    market.demand -= 1;
+                                } else {
+                                    idx += 1;
+                                }
+                            }
+                            if market.demand == 0 {
+                                occupied.remove();
+                                events.push(Event::MarketSatisfied { coord });
+                                truck.out_of_bounds_immunity = true;
+                            }
+                        }
+                        truck.facing = truck.facing + Angle::Back;
+                    }
+                    Cell::Warehouse(warehouse) => {
+                        if truck.cargo.is_empty() {
+                            // Take out as much as fits, oldest first
+                            while !warehouse.items.is_empty() && !truck.is_full() {
+                                let item = warehouse.items.remove(0);
+                                events.push(Event::Retrieved {
+                                    truck: truck.id,
+                                    coord,
+                                    item: item.clone(),
+                                });
+                                truck.cargo.push(item);
+                            }
+                        } else {
+                            // Leave whatever there's room for, and hang onto the rest
+                            let mut idx = 0;
+                            while idx < truck.cargo.len()
+                                && warehouse.items.len() < warehouse.capacity
+                            {
+                                if warehouse.filter.matches(&truck.cargo[idx]) {
+                                    let item = truck.cargo.remove(idx);
+                                    events.push(Event::Stored {
+                                        truck: truck.id,
+                                        coord,
+                                        item: item.clone(),
+                                    });
+                                    warehouse.items.push(item);
+                                } else {
+                                    idx += 1;
+                                }
+                            }
+                        }
+                        truck.facing = truck.facing + Angle::Back;
+                    }
+                    Cell::Instruction(instruction) => match *instruction {
+                        Instruction::Rotate(rot) => {
+                            truck.facing = truck.facing + rot;
+                        }
+                        Instruction::Direct(dir) => {
+                            truck.facing = dir;
+                        }
+                        Instruction::Sort {
+                            ref filter,
+                            matched,
+                            unmatched,
+                        } => {
+                            let is_match = truck.cargo.iter().any(|item| filter.matches(item));
+                            truck.facing = if is_match { matched } else { unmatched };
+                        }
+                        Instruction::Switch {
+                            ref directions,
+                            ref mut next,
+                        } => {
+                            if let Some(&dir) = directions.get(*next) {
+                                truck.facing = dir;
+                            }
+                            *next = (*next + 1) % directions.len().max(1);
+                        }
+                        // These hold trucks up before they move off again
+                        Instruction::Signal { .. } | Instruction::Light { .. } => {}
+                        Instruction::Boost => {
+                            truck.speed = (truck.speed * SPEED_CHANGE).min(MAX_SPEED);
+                        }
+                        Instruction::Slow => {
+                            truck.speed = (truck.speed / SPEED_CHANGE).max(MIN_SPEED);
+                        }
+                        Instruction::Tunnel { to } => tunnel_to = Some(to),
+                        // Just drive on over (or under)
+                        Instruction::Bridge { .. } => {}
+                        Instruction::Counter {
+                            every,
+                            straight,
+                            redirect,
+                            ref mut count,
+                        } => {
+                            *count += 1;
+                            if *count >= every {
+                                truck.facing = redirect;
+                                *count = 0;
+                            } else {
+                                truck.facing = straight;
+                            }
+                        }
+                        Instruction::Dump { recycle } => {
+                            for item in truck.cargo.drain(..) {
+                                events.push(if recycle {
+                                    Event::Recycled {
+                                        truck: truck.id,
+                                        coord,
+                                        item,
+                                        price: RECYCLE_PRICE,
+                                    }
+                                } else {
+                                    Event::Dumped {
+                                        truck: truck.id,
+                                        coord,
+                                        item,
+                                        fee: DUMP_FEE,
+                                    }
+                                });
+                            }
+                        }
+                        Instruction::Shunt(shunt) => {
+                            let target = truck.position + shunt;
+                            if !Board::is_passable(cells, target, truck.out_of_bounds_immunity) {
+                                events.push(Board::collision_event(cells, truck.id, target));
+                                // oh no, clobber this position into wreckage
+                                if cells.contains_key(&truck.position) {
+                                    cells.insert(truck.position, Cell::Wreckage);
+                                }
+                                // delt trucc
+                                return false;
+                            } else {
+                                truck.position = target;
+                            }
+                        }
+                    },
+                    Cell::Empty => {}
+                    Cell::Wreckage => {
+                        // :HOW:
+                        println!("A truck has fallen into the wreckage in Error City!");
+                        return false;
+                    }
+                }
+            }
+        }
+
+        if let Some(to) = tunnel_to {
+            // Down the tunnel, if the other end's still there
+            let coord = truck.position;
+            if matches!(
+                cells.get(&to),
+                Some(Cell::Instruction(Instruction::Tunnel { to: back })) if *back == coord
+            ) {
+                truck.underground = coord.distance(to).max(1) as u32 * TUNNEL_TICKS_PER_HEX;
+                truck.position = to;
+                truck.out_of_bounds_immunity = false;
+            }
+        }
+
+        true
+    }
+
+    /// Crash any trucks that ran into each other.
+    fn crash_trucks(&mut self, events: &mut Vec<Event>) {
+        // Check for collisions
+        let mut collided_truck_idxes = Vec::new();
+        for (idx, truck) in self.trucks.iter().enumerate() {
+            let (x, y) = truck.get_xy();
+            // prevent collisions with self
+            for other in self.trucks.iter().skip(idx + 1) {
+                if !self.can_collide(truck, other) {
+                    continue;
+                }
+                if truck.waited > 0 && other.waited > 0 && truck.position != other.position {
+                    // Trucks standing still in a queue don't crash into each other
+                    continue;
+                }
+                let (ox, oy) = other.get_xy();
+                let radius = truck.class.crash_radius() + other.class.crash_radius();
+                if (x - ox).powi(2) + (y - oy).powi(2) < radius.powi(2) {
+                    // oeuf
+                    let hex = truck.get_hex();
+                    events.push(Event::Crash {
+                        truck: truck.id,
+                        other: other.id,
+                        coord: hex,
+                    });
+                    collided_truck_idxes.push(idx);
+                    if self.cells.contains_key(&hex) {
+                        self.cells.insert(hex, Cell::Wreckage);
+                    }
+                    // the other piece of wreckage will be inserted by the other truck.
+                }
+            }
+        }
+        for idx in (0..self.trucks.len()).rev() {
+            if collided_truck_idxes.contains(&idx) {
+                let truck = self.trucks.remove(idx);
+                self.retired.push(truck);
+            }
+        }
+    }
+
+    /// Which layer a truck is driving on: `1` on top of a bridge, `0` everywhere else.
+    pub fn truck_layer(&self, truck: &Truck) -> u8 {
+        u8::from(self.is_bridge_for(truck, truck.get_hex()))
+    }
+
+    /// The bridge a truck is driving onto, over or off of, if it's going along one.
+    fn bridge_ramp(&self, truck: &Truck) -> Option<Coordinate> {
+        let target = truck.position + truck.facing;
+        if self.is_bridge_for(truck, truck.position) {
+            Some(truck.position)
+        } else if truck.move_progress > 0.0 && self.is_bridge_for(truck, target) {
+            Some(target)
+        } else {
+            None
+        }
+    }
+
+    /// Check if `coord` has a bridge on it that goes the way the truck is facing
+    fn is_bridge_for(&self, truck: &Truck, coord: Coordinate) -> bool {
+        match self.cells.get(&coord) {
+            Some(Cell::Instruction(Instruction::Bridge { over })) => {
+                truck.facing == *over || truck.facing == *over + Angle::Back
+            }
+            _ => false,
+        }
+    }
+
+    /// Check if two trucks are in each other's way at all, ignoring how far apart they are.
+    ///
+    /// Trucks in tunnels pass under everything, and trucks on different layers pass over each other.
+    /// A truck on its way onto or off of a bridge is still clear of anything
+    /// crossing underneath that bridge.
+    fn can_collide(&self, truck: &Truck, other: &Truck) -> bool {
+        if truck.underground > 0 || other.underground > 0 {
+            return false;
+        }
+        let ramp = self.bridge_ramp(truck);
+        let other_ramp = self.bridge_ramp(other);
+        if ramp.is_some() && ramp == other_ramp {
+            // Both on the same bridge
+            return true;
+        }
+        self.truck_layer(truck) == self.truck_layer(other)
+            && ramp != Some(other.get_hex())
+            && other_ramp != Some(truck.get_hex())
+    }
+
+    /// Work out which trucks have to stand still this tick.
+    ///
+    /// A truck stands still if it's sitting on a signal or red light that's holding it,
+    /// or if it's right behind a truck that's standing still
+    /// (or would run into one by moving, wherever it's got to in its hex).
+    fn held_trucks(&self) -> Vec<bool> {
+        let mut held = self
+            .trucks
+            .iter()
+            .map(|truck| {
+                truck.underground == 0 && truck.move_progress == 0.0 && self.is_held_by_cell(truck)
+            })
+            .collect::<Vec<_>>();
+
+        if self.safe_following {
+            for (idx, truck) in self.trucks.iter().enumerate() {
+                if !held[idx] && self.is_tailgating(truck) {
+                    held[idx] = true;
+                }
+            }
+        }
+
+        // Back the queues up
+        loop {
+            let mut changed = false;
+            for (idx, truck) in self.trucks.iter().enumerate() {
+                if held[idx] || truck.underground > 0 {
+                    continue;
+                }
+                let ahead = truck.position + truck.facing;
+                let blocked = self
+                    .trucks
+                    .iter()
+                    .zip(held.iter())
+                    .any(|(other, &other_held)| {
+                        other_held
+                            && ((truck.move_progress == 0.0 && other.position == ahead)
+                                || self.would_close_in(truck, other, 0.0))
+                    });
+                if blocked {
+                    held[idx] = true;
+                    changed = true;
+                }
+            }
+            if !changed {
+                return held;
+            }
+        }
+    }
+
+    /// Check if moving this tick would take a truck too close to the one in front of it,
+    /// going the same way on the same level.
+    ///
+    /// Only looks at where everything is at the start of the tick,
+    /// so it doesn't matter what order the trucks get checked in.
+    fn is_tailgating(&self, truck: &Truck) -> bool {
+        self.trucks.iter().any(|other| {
+            other.facing == truck.facing && self.would_close_in(truck, other, FOLLOW_GAP)
+        })
+    }
+
+    /// Check if moving this tick would take a truck to within `gap` of touching `other`,
+    /// if `other` were to stay where it is.
+    fn would_close_in(&self, truck: &Truck, other: &Truck, gap: f32) -> bool {
+        if other.id == truck.id || !self.can_collide(truck, other) {
+            return false;
+        }
+        let (x, y) = truck.get_xy();
+        let (nx, ny) = truck.get_xy_ahead(truck.speed);
+        let (ox, oy) = other.get_xy();
+        let in_front = (ox - x) * (nx - x) + (oy - y) * (ny - y) > 0.0;
+        let reach = truck.class.crash_radius() + other.class.crash_radius() + gap;
+        in_front && (ox - nx).powi(2) + (oy - ny).powi(2) < reach.powi(2)
+    }
+
+    /// Check if the cell a truck is sitting on wants it to wait
+    fn is_held_by_cell(&self, truck: &Truck) -> bool {
+        match self.cells.get(&truck.position) {
+            Some(Cell::Instruction(Instruction::Signal { hold: Some(ticks) })) => {
+                truck.waited < *ticks
+            }
+            Some(Cell::Instruction(Instruction::Signal { hold: None })) => {
+                let ahead = truck.position + truck.facing;
+                self.trucks.iter().any(|other| {
+                    other.id != truck.id
+                        && other.underground == 0
+                        && (other.position == ahead || other.get_hex() == ahead)
+                })
+            }
+            Some(Cell::Instruction(Instruction::Light { green, .. })) => !green,
+            _ => false,
+        }
+    }
+
+    /// Check if the given hex is on the board proper, where the player can put things.
+    pub fn is_on_board(&self, coord: Coordinate) -> bool {
+        coord.distance(Coordinate::new(0, 0)) <= self.radius as i32
+    }
+
+    /// What it's called when a truck can't drive into `coord`
+    fn collision_event(
+        cells: &HashMap<Coordinate, Cell>,
+        truck: TruckId,
+        coord: Coordinate,
+    ) -> Event {
+        if cells.contains_key(&coord) {
+            Event::HitWreckage { truck, coord }
+        } else {
+            Event::DroveOffBoard { truck, coord }
+        }
+    }
+
+    /// Check if the given hex can be driven through
+    fn is_passable(
+        cells: &HashMap<Coordinate, Cell>,
+        coord: Coordinate,
+        out_of_bounds_immunity: bool,
+    ) -> bool {
+        let target = cells.get(&coord);
+        match target {
+            None => out_of_bounds_immunity,
+            Some(Cell::Wreckage) => false,
+            _ => true,
+        }
+    }
+
+    /// Add a new market or factory, accounting for current buildings.
+    ///
+    /// Return where it went, if there was anywhere to put it.
+    fn add_building(&mut self, frames_elapsed: u64, rng: &mut GameRng) -> Option<Coordinate> {
+        // Count the number of markets. If there are markets without a factory for them, add the factory
+        // This maps items to bitmaps. Bit 1 = factory, bit 2 = market.
+        // (It's ordered so picking from it is the same every time.)
+        let mut item_statuses = BTreeMap::<_, u8>::new();
+        for (_coord, cell) in self.cells.iter() {
+            match cell {
+                Cell::Factory(f) => {
+                    *item_statuses.entry(&f.product).or_default() |= 0b01;
+                }
+                Cell::Market(Market {
+                    request: ItemFilter::Specific(item),
+                    ..
+                }) => {
+                    *item_statuses.entry(item).or_default() |= 0b10;
+                }
+                _ => {}
+            }
+        }
+
+        let lacking_factory = item_statuses
+            .into_iter()
+            .filter(|(_item, bitmask)| *bitmask == 0b10)
+            .collect::<Vec<_>>();
+        let new_building = if lacking_factory.is_empty() {
+            // Nothing is lacking, make up something totally random
+            if rng.gen_bool(0.6) {
+                Cell::Factory(Factory::generate(Item::sample(rng), frames_elapsed, rng))
+            } else {
+                Cell::Market(Market::generate(
+                    ItemFilter::sample(rng),
+                    frames_elapsed,
+                    rng,
+                ))
+            }
+        } else {
+            Cell::Factory(Factory::generate(
+                lacking_factory[rng.gen_range(0..lacking_factory.len())]
+                    .0
+                    .to_owned(),
+                frames_elapsed,
+                rng,
+            ))
+        };
+
+        // Insert it somewhere, hopefully
+        let mut canidates = Coordinate::new(0, 0)
+            .ring_iter(self.radius as i32 + 1, Spin::CW(Direction::XY))
+            .collect::<Vec<_>>();
+        // shut
+        #[allow(clippy::map_entry)]
+        loop {
+            let end = match canidates.len() {
+                0 => return None,
+                it => it,
+            };
+            let coord = canidates.remove(rng.gen_range(0..end));
+            if !self.cells.contains_key(&coord) {
+                self.cells.insert(coord, new_building);
+                return Some(coord);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{cards::ROTATE_ANGLES, *};
+
+    /// A board with nothing on it
+    fn empty_board() -> Board {
+        Board {
+            cells: Coordinate::new(0, 0)
+                .range_iter(5)
+                .map(|coord| (coord, Cell::Empty))
+                .collect(),
+            trucks: vec![],
+            treads: vec![],
+            radius: 5,
+            next_truck_id: 0,
+            safe_following: false,
+            retired: vec![],
+        }
+    }
+
+    /// Put a truck in the middle facing `facing`, and run the board until it's sitting on `coord`
+    fn drive_to(board: &mut Board, facing: Direction, coord: Coordinate) -> Truck {
+        board.add_truck(Truck::from_rot(
+            Coordinate::new(0, 0),
+            TruckClass::Truck,
+            None,
+            facing.to_int(),
+        ));
+        let mut events = Vec::new();
+        for tick in 0..1000 {
+            board.step_trucks(tick, &mut events);
+            assert!(events.is_empty(), "{:?}", events);
+            let truck = &board.trucks[0];
+            if truck.position == coord && truck.move_progress == 0.0 {
+                return truck.clone();
+            }
+        }
+        panic!("truck never got to {:?}", coord);
+    }
+
+    #[test]
+    fn rotate_dial_skips_forward() {
+        for rotation in -10..10 {
+            let dials = Dials {
+                rotation,
+                ..Default::default()
+            };
+            let instr = CardInstruction::Rotate.to_instruction(dials);
+            assert_ne!(instr, Instruction::Rotate(Angle::Forward));
+
+            // and it comes back out the same
+            let (_, back) = CardInstruction::from_instruction(&instr);
+            assert_eq!(CardInstruction::Rotate.to_instruction(back), instr);
+        }
+    }
+
+    #[test]
+    fn rotate_turns_relative_to_facing() {
+        for facing in Direction::all() {
+            for &angle in ROTATE_ANGLES.iter() {
+                let mut board = empty_board();
+                let rotate = Coordinate::new(0, 0) + *facing;
+                board
+                    .cells
+                    .insert(rotate, Cell::Instruction(Instruction::Rotate(angle)));
+
+                let truck = drive_to(&mut board, *facing, rotate);
+                assert_eq!(truck.facing, *facing + angle);
+            }
+        }
+    }
+
+    #[test]
+    fn rotates_add_up() {
+        for &first in ROTATE_ANGLES.iter() {
+            for &second in ROTATE_ANGLES.iter() {
+                let mut board = empty_board();
+                let a = Coordinate::new(0, 0) + Direction::XY;
+                let b = a + (Direction::XY + first);
+                board
+                    .cells
+                    .insert(a, Cell::Instruction(Instruction::Rotate(first)));
+                board
+                    .cells
+                    .insert(b, Cell::Instruction(Instruction::Rotate(second)));
+
+                let truck = drive_to(&mut board, Direction::XY, b);
+                assert_eq!(truck.facing, Direction::XY + first + second);
+            }
+        }
+    }
+
+    #[test]
+    fn direct_then_rotate() {
+        for dir in Direction::all() {
+            for &angle in ROTATE_ANGLES.iter() {
+                let mut board = empty_board();
+                let direct = Coordinate::new(0, 0) + Direction::XY;
+                let rotate = direct + *dir;
+                board
+                    .cells
+                    .insert(direct, Cell::Instruction(Instruction::Direct(*dir)));
+                board
+                    .cells
+                    .insert(rotate, Cell::Instruction(Instruction::Rotate(angle)));
+
+                // Turns from wherever the direct pointed it
+                let truck = drive_to(&mut board, Direction::XY, rotate);
+                assert_eq!(truck.facing, *dir + angle);
+            }
+        }
+    }
+
+    #[test]
+    fn rotate_then_direct() {
+        for &angle in ROTATE_ANGLES.iter() {
+            for dir in Direction::all() {
+                let mut board = empty_board();
+                let rotate = Coordinate::new(0, 0) + Direction::XY;
+                let direct = rotate + (Direction::XY + angle);
+                board
+                    .cells
+                    .insert(rotate, Cell::Instruction(Instruction::Rotate(angle)));
+                board
+                    .cells
+                    .insert(direct, Cell::Instruction(Instruction::Direct(*dir)));
+
+                // Directs don't care which way it was facing
+                let truck = drive_to(&mut board, Direction::XY, direct);
+                assert_eq!(truck.facing, *dir);
+            }
+        }
+    }
+
+    #[test]
+    fn rotate_then_shunt() {
+        for &angle in ROTATE_ANGLES.iter() {
+            for shunt in Direction::all() {
+                let mut board = empty_board();
+                let rotate = Coordinate::new(0, 0) + Direction::XY;
+                let shunt_at = rotate + (Direction::XY + angle);
+                board
+                    .cells
+                    .insert(rotate, Cell::Instruction(Instruction::Rotate(angle)));
+                board
+                    .cells
+                    .insert(shunt_at, Cell::Instruction(Instruction::Shunt(*shunt)));
+
+                // Shunted over, but still facing the way the rotate turned it
+                let truck = drive_to(&mut board, Direction::XY, shunt_at + *shunt);
+                assert_eq!(truck.facing, Direction::XY + angle);
+            }
+        }
+    }
+
+    /// A fresh game with nothing on the board, so tests can set it up how they like
+    fn empty_sim(seed: u64) -> Simulation {
+        let mut sim = Simulation::new(seed);
+        sim.board = empty_board();
+        sim
+    }
+
+    /// Run the game for `ticks` ticks, and return everything that happened
+    fn run(sim: &mut Simulation, ticks: u64) -> Vec<Event> {
+        (0..ticks).flat_map(|_| sim.tick()).collect()
+    }
+
+    #[test]
+    fn truck_turns_on_direct() {
+        let mut sim = empty_sim(0);
+        let direct = Coordinate::new(0, 0) + Direction::XY;
+        // The starting hand is two trucks, then a direct
+        assert!(sim.apply(Command::PlayCard {
+            hand_idx: 2,
+            coord: direct,
+            dials: Dials {
+                rotation: Direction::ZY.to_int(),
+                ..Default::default()
+            },
+        }));
+        assert!(sim.apply(Command::PlayCard {
+            hand_idx: 0,
+            coord: Coordinate::new(0, 0),
+            dials: Dials {
+                rotation: Direction::XY.to_int(),
+                ..Default::default()
+            },
+        }));
+
+        let events = run(&mut sim, 60);
+        assert!(events.is_empty(), "{:?}", events);
+        let truck = &sim.board.trucks[0];
+        assert_eq!(truck.position, direct);
+        assert_eq!(truck.facing, Direction::ZY);
+        assert!(truck.move_progress > 0.0);
+    }
+
+    #[test]
+    fn same_seed_same_game() {
+        let play = |seed| {
+            let mut sim = Simulation::new(seed);
+            for rotation in [0, 3] {
+                sim.apply(Command::PlayCard {
+                    hand_idx: 0,
+                    coord: Coordinate::new(0, 0) + Direction::from_int(rotation),
+                    dials: Dials {
+                        rotation,
+                        ..Default::default()
+                    },
+                });
+            }
+            let events = run(&mut sim, TAX_TIMER * 3);
+            (events, sim.player_info.money, sim.player_info.tax)
+        };
+
+        let (events, money, tax) = play(42);
+        assert!(!events.is_empty());
+        assert_eq!(play(42), (events, money, tax));
+    }
+
+    #[test]
+    fn delivery_pays_out() {
+        let mut sim = empty_sim(0);
+        let mut rng = GameRng::seed_from_u64(0);
+        let origin = Coordinate::new(0, 0);
+        sim.board.cells.insert(
+            origin + Direction::XY,
+            Cell::Factory(Factory {
+                product: Item::Orange,
+                stock: 5,
+            }),
+        );
+        sim.board.cells.insert(
+            origin + Direction::YX,
+            Cell::Market(Market::generate(ItemFilter::Any, 1000, &mut rng)),
+        );
+        assert!(sim.apply(Command::PlayCard {
+            hand_idx: 0,
+            coord: origin,
+            dials: Dials {
+                rotation: Direction::XY.to_int(),
+                ..Default::default()
+            },
+        }));
+        let money = sim.player_info.money;
+
+        // Out to the factory, back through the middle, and into the market
+        let events = run(&mut sim, 130);
+        let paid = events
+            .iter()
+            .filter(|event| matches!(event, Event::Delivered { .. }))
+            .map(Event::money)
+            .sum::<u32>();
+        assert!(paid > 0, "{:?}", events);
+        assert_eq!(sim.player_info.money, money + paid);
+        assert_eq!(sim.player_info.tax, 0);
+    }
+
+    #[test]
+    fn bridge_keeps_trucks_apart() {
+        let bridge = Coordinate::new(0, 0);
+        for offset in 0..40 {
+            let mut board = empty_board();
+            board.cells.insert(
+                bridge,
+                Cell::Instruction(Instruction::Bridge {
+                    over: Direction::XY,
+                }),
+            );
+            // One goes over the bridge and one goes under it, `offset` ticks behind
+            board.add_truck(Truck::from_rot(
+                bridge + Direction::YX + Direction::YX,
+                TruckClass::Truck,
+                None,
+                Direction::XY.to_int(),
+            ));
+            let mut events = Vec::new();
+            for tick in 0..offset + 160 {
+                if tick == offset {
+                    board.add_truck(Truck::from_rot(
+                        bridge + Direction::ZY + Direction::ZY,
+                        TruckClass::Truck,
+                        None,
+                        Direction::YZ.to_int(),
+                    ));
+                }
+                board.step_trucks(tick, &mut events);
+            }
+            assert!(
+                !events
+                    .iter()
+                    .any(|event| matches!(event, Event::Crash { .. })),
+                "offset {}: {:?}",
+                offset,
+                events
+            );
+            assert_eq!(board.trucks.len(), 2);
+        }
+
+        // Trucks on their way onto the bridge still crash into things on the hex before it,
+        // even once they've started up the ramp
+        let before = bridge + Direction::YX;
+        for offset in 0..56 {
+            let mut board = empty_board();
+            board.cells.insert(
+                bridge,
+                Cell::Instruction(Instruction::Bridge {
+                    over: Direction::XY,
+                }),
+            );
+            // One heads over the bridge, and one cuts across in front of it around the same time
+            let mut events = Vec::new();
+            for tick in 0..160 {
+                if tick == 15 {
+                    board.add_truck(Truck::from_rot(
+                        before + Direction::YX + Direction::YX,
+                        TruckClass::Truck,
+                        None,
+                        Direction::XY.to_int(),
+                    ));
+                }
+                if tick == offset {
+                    board.add_truck(Truck::from_rot(
+                        before + Direction::XZ + Direction::XZ,
+                        TruckClass::Truck,
+                        None,
+                        Direction::ZX.to_int(),
+                    ));
+                }
+                board.step_trucks(tick, &mut events);
+            }
+            assert!(
+                events
+                    .iter()
+                    .any(|event| matches!(event, Event::Crash { .. })),
+                "offset {}: {:?}",
+                offset,
+                events
+            );
+        }
+    }
+
+    #[test]
+    fn cleanup_resets_counter() {
+        let mut board = empty_board();
+        let coord = Coordinate::new(0, 0) + Direction::XY;
+        let counter = |count| {
+            Cell::Instruction(Instruction::Counter {
+                every: 3,
+                straight: Direction::XY,
+                redirect: Direction::ZY,
+                count,
+            })
+        };
+        board.cells.insert(coord, counter(2));
+
+        // First cleanup starts the count over
+        assert!(board.play_card(&Card::Cleanup, coord, Dials::default()));
+        assert_eq!(board.cells.get(&coord), Some(&counter(0)));
+
+        // Second one clears it off
+        assert!(board.play_card(&Card::Cleanup, coord, Dials::default()));
+        assert_eq!(board.cells.get(&coord), Some(&Cell::Empty));
+    }
+
+    #[test]
+    fn cant_afford_to_move_truck() {
+        let mut sim = empty_sim(0);
+        let origin = Coordinate::new(0, 0);
+        assert!(sim.apply(Command::PlayCard {
+            hand_idx: 0,
+            coord: origin,
+            dials: Dials::default(),
+        }));
+        let truck = sim.board.trucks[0].id;
+        assert!(sim.apply(Command::LiftTruck { truck }));
+        sim.player_info.money = MOVE_TRUCK_FEE - 1;
+
+        // It doesn't go down, and doesn't get sold either
+        assert!(!sim.apply(Command::PutDownTruck {
+            coord: Some(origin + Direction::XY),
+            rotation: 0,
+        }));
+        assert!(sim.board.trucks.is_empty());
+        assert_eq!(sim.player_info.money, MOVE_TRUCK_FEE - 1);
+        assert_eq!(
+            sim.player_info.lifted_truck.as_ref().map(|truck| truck.id),
+            Some(truck)
+        );
+
+        // Dropping it off the board still sells it
+        assert!(sim.apply(Command::PutDownTruck {
+            coord: None,
+            rotation: 0,
+        }));
+        assert!(sim.player_info.lifted_truck.is_none());
+        assert!(sim.player_info.money > MOVE_TRUCK_FEE - 1);
+    }
+
+    #[test]
+    fn trucks_queue_behind_signal() {
+        let signal = Coordinate::new(0, 0) + Direction::XY;
+        // However far behind the second truck is (once they're not touching),
+        // even partway into a hex, it waits for the first one instead of running into it
+        for lag in 22..80 {
+            let mut board = empty_board();
+            board.cells.insert(
+                signal,
+                Cell::Instruction(Instruction::Signal { hold: Some(100) }),
+            );
+            let mut events = Vec::new();
+            for tick in 0..200 {
+                if tick == 0 || tick == lag {
+                    board.add_truck(Truck::from_rot(
+                        Coordinate::new(0, 0) + Direction::YX,
+                        TruckClass::Truck,
+                        None,
+                        Direction::XY.to_int(),
+                    ));
+                }
+                board.step_trucks(tick, &mut events);
+            }
+            assert!(
+                !events
+                    .iter()
+                    .any(|event| matches!(event, Event::Crash { .. })),
+                "lag {}: {:?}",
+                lag,
+                events
+            );
+            assert_eq!(board.trucks.len(), 2);
+        }
+    }
+
+    #[test]
+    fn queues_arent_forecast_as_crashes() {
+        let mut board = empty_board();
+        let signal = Coordinate::new(0, 0) + Direction::XY;
+        board.cells.insert(
+            signal,
+            Cell::Instruction(Instruction::Signal { hold: Some(100) }),
+        );
+        let mut events = Vec::new();
+        for tick in 0..30 {
+            if tick == 0 || tick == 25 {
+                board.add_truck(Truck::from_rot(
+                    Coordinate::new(0, 0) + Direction::YX,
+                    TruckClass::Truck,
+                    None,
+                    Direction::XY.to_int(),
+                ));
+            }
+            board.step_trucks(tick, &mut events);
+        }
+
+        // They end up bumper to bumper at the signal, which is fine
+        let forecast = board.forecast(30, 3);
+        assert!(forecast.conflicts.is_empty(), "{:?}", forecast.conflicts);
+        assert!(forecast.routes.iter().all(|route| route.fate.is_none()));
+    }
+
+    #[test]
+    fn stats_kept_for_wrecked_trucks() {
+        let mut board = empty_board();
+        let dump = Coordinate::new(0, 0) + Direction::XY;
+        board.cells.insert(
+            dump,
+            Cell::Instruction(Instruction::Dump { recycle: false }),
+        );
+        board.cells.insert(dump + Direction::XY, Cell::Wreckage);
+        board.add_truck(Truck::from_rot(
+            Coordinate::new(0, 0),
+            TruckClass::Truck,
+            Some(Item::Orange),
+            Direction::XY.to_int(),
+        ));
+
+        // Pays to dump its orange, then drives straight into the wreckage
+        let mut events = Vec::new();
+        for tick in 0..100 {
+            board.step_trucks(tick, &mut events);
+        }
+        assert!(board.trucks.is_empty(), "{:?}", events);
+        let stats = &board.retired[0].stats;
+        assert_eq!(stats.revenue, -i64::from(DUMP_FEE));
+        assert_eq!(stats.tax, TAX_COLLISION);
+    }
+}
@@ -0,0 +1,90 @@
+//! Recording games and playing them back.
+//!
+//! Because the whole game comes out of the seed, all we need to remember
+//! is what the player did and when.
+
+use super::{events::Event, Command, Simulation};
+
+use serde::{Deserialize, Serialize};
+
+/// Everything needed to play a game back exactly.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Recording {
+    /// Seed the game was started with
+    pub seed: u64,
+    /// `(tick, command)` pairs in the order they were given.
+    /// The tick is how many ticks had passed when the command went in.
+    pub commands: Vec<(u64, Command)>,
+    /// How many ticks the game has run for
+    pub length: u64,
+}
+
+impl Recording {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            seed,
+            commands: Vec::new(),
+            length: 0,
+        }
+    }
+}
+
+/// A recording being played back.
+pub struct Playback {
+    pub recording: Recording,
+    /// The game being re-run
+    pub sim: Simulation,
+    /// Index of the next command to hand to the sim
+    next_command: usize,
+}
+
+impl Playback {
+    pub fn new(recording: Recording) -> Self {
+        Self {
+            sim: Simulation::new(recording.seed),
+            recording,
+            next_command: 0,
+        }
+    }
+
+    /// Feed in any commands due now, then advance one tick.
+    ///
+    /// Does nothing once the recording has run out.
+    /// Return everything that happened on the board.
+    pub fn step(&mut self) -> Vec<Event> {
+        while let Some((tick, command)) = self.recording.commands.get(self.next_command) {
+            if *tick != self.sim.ticks {
+                break;
+            }
+            self.sim.apply(command.clone());
+            self.next_command += 1;
+        }
+
+        if self.is_finished() {
+            Vec::new()
+        } else {
+            self.sim.tick()
+        }
+    }
+
+    /// Check if there's nothing left to play
+    pub fn is_finished(&self) -> bool {
+        self.sim.game_over
+            || (self.sim.ticks >= self.recording.length
+                && self.next_command >= self.recording.commands.len())
+    }
+
+    /// Jump to the given tick.
+    ///
+    /// Going backwards means starting over from the top, because there's no undoing a tick.
+    pub fn seek(&mut self, tick: u64) {
+        let tick = tick.min(self.recording.length);
+        if tick < self.sim.ticks {
+            self.sim = Simulation::new(self.recording.seed);
+            self.next_command = 0;
+        }
+        while self.sim.ticks < tick && !self.is_finished() {
+            self.step();
+        }
+    }
+}
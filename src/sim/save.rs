@@ -0,0 +1,158 @@
+//! Saving a game in progress to disk and picking it back up later.
+
+use super::Simulation;
+
+use directories::ProjectDirs;
+use serde::{Deserialize, Serialize};
+
+use std::{fs, io, path::PathBuf};
+
+/// Bump this whenever the save format changes,
+/// so old saves get turned away instead of misread.
+/// New fields don't get defaults to fill them in from old saves; this is what handles it.
+const SAVE_VERSION: u32 = 15;
+
+#[derive(Serialize, Deserialize)]
+struct SaveFile<T> {
+    version: u32,
+    game: T,
+}
+
+/// Just enough of a save file to check if we can read the rest of it.
+#[derive(Deserialize)]
+struct SaveHeader {
+    version: u32,
+}
+
+/// Where the save file lives, if this platform has anywhere to put it
+fn save_path() -> Option<PathBuf> {
+    ProjectDirs::from("", "", "gridlock").map(|dirs| dirs.data_dir().join("save.ron"))
+}
+
+fn no_save_dir() -> io::Error {
+    io::Error::new(io::ErrorKind::NotFound, "no data directory to save in")
+}
+
+fn invalid(err: impl std::error::Error + Send + Sync + 'static) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, err)
+}
+
+/// Check if there's a save to continue from.
+pub fn exists() -> bool {
+    save_path().is_some_and(|path| path.is_file())
+}
+
+/// Write the game to the save file, replacing whatever was there.
+pub fn save(sim: &Simulation) -> io::Result<()> {
+    let path = save_path().ok_or_else(no_save_dir)?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+
+    fs::write(path, to_text(sim)?)
+}
+
+/// Read the game back out of the save file.
+///
+/// Returns `Ok(None)` if there isn't one.
+pub fn load() -> io::Result<Option<Simulation>> {
+    let path = save_path().ok_or_else(no_save_dir)?;
+    let text = match fs::read_to_string(path) {
+        Ok(it) => it,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(err) => return Err(err),
+    };
+    from_text(&text).map(Some)
+}
+
+/// Write the game out as the text that goes in the save file.
+fn to_text(sim: &Simulation) -> io::Result<String> {
+    let file = SaveFile {
+        version: SAVE_VERSION,
+        game: sim,
+    };
+    ron::ser::to_string_pretty(&file, Default::default()).map_err(invalid)
+}
+
+/// Read the game back out of save file text, if it's a version this game can read.
+fn from_text(text: &str) -> io::Result<Simulation> {
+    let header: SaveHeader = ron::from_str(text).map_err(invalid)?;
+    if header.version != SAVE_VERSION {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!(
+                "save is version {} but this game reads version {}",
+                header.version, SAVE_VERSION
+            ),
+        ));
+    }
+    let file: SaveFile<Simulation> = ron::from_str(text).map_err(invalid)?;
+    Ok(file.game)
+}
+
+/// Get rid of the save file, if there is one.
+pub fn delete() -> io::Result<()> {
+    let path = save_path().ok_or_else(no_save_dir)?;
+    match fs::remove_file(path) {
+        Err(err) if err.kind() != io::ErrorKind::NotFound => Err(err),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sim::{cards::Dials, Command};
+
+    use hex2d::{Coordinate, Direction};
+    use rand::Rng;
+
+    #[test]
+    fn resumed_game_plays_the_same() {
+        let mut sim = Simulation::new(7);
+        for (hand_idx, rotation) in [(0, 0), (0, 3)] {
+            sim.apply(Command::PlayCard {
+                hand_idx,
+                coord: Coordinate::new(0, 0) + Direction::from_int(rotation),
+                dials: Dials {
+                    rotation,
+                    ..Default::default()
+                },
+            });
+        }
+        for _ in 0..500 {
+            sim.tick();
+        }
+
+        let mut loaded = from_text(&to_text(&sim).unwrap()).unwrap();
+        for _ in 0..2000 {
+            assert_eq!(sim.tick(), loaded.tick());
+        }
+
+        assert_eq!(sim.ticks, loaded.ticks);
+        assert_eq!(sim.player_info.money, loaded.player_info.money);
+        assert_eq!(sim.player_info.tax, loaded.player_info.tax);
+        assert_eq!(
+            format!("{:?}", sim.player_info.hand),
+            format!("{:?}", loaded.player_info.hand)
+        );
+        // Markets' prices included
+        assert!(sim.board.cells == loaded.board.cells);
+        assert_eq!(
+            format!("{:?}", sim.board.trucks),
+            format!("{:?}", loaded.board.trucks)
+        );
+        // The RNG picks up from the same place
+        assert_eq!(sim.rng.gen::<u64>(), loaded.rng.gen::<u64>());
+    }
+
+    #[test]
+    fn other_versions_are_turned_away() {
+        let text = to_text(&Simulation::new(7)).unwrap().replacen(
+            &format!("version: {}", SAVE_VERSION),
+            &format!("version: {}", SAVE_VERSION - 1),
+            1,
+        );
+        assert!(from_text(&text).is_err());
+    }
+}
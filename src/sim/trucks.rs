@@ -0,0 +1,230 @@
+use std::f32::consts::TAU;
+
+use hex2d::{Coordinate, Direction, Spacing};
+use serde::{Deserialize, Serialize};
+
+use super::{cards::Card, economy::Item, events::Event, TRUCK_REFUND_PERCENT};
+
+/// How far a normal truck moves per tick
+pub const BASE_SPEED: f32 = 1.0 / 40.0;
+/// Slowest a truck can be slowed down to
+pub const MIN_SPEED: f32 = BASE_SPEED / 4.0;
+/// Fastest a truck can be sped up to
+pub const MAX_SPEED: f32 = BASE_SPEED * 4.0;
+/// How much boosting multiplies a truck's speed by (and slowing divides it by)
+pub const SPEED_CHANGE: f32 = 2.0;
+/// How many ticks perishable cargo lasts outside of a fridge
+pub const SHELF_LIFE: u32 = 60 * 15;
+
+/// Names one truck for as long as it's on the board.
+/// Never reused within a game.
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Default, Serialize, Deserialize,
+)]
+pub struct TruckId(pub u64);
+
+/// A truck carrying an item around.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Truck {
+    /// Which truck this is. Handed out by `Board::add_truck`.
+    pub id: TruckId,
+    /// What kind of truck it is
+    pub class: TruckClass,
+    /// What it's holding, oldest first
+    pub cargo: Vec<Item>,
+    /// How many ticks the perishable cargo's been going off for
+    pub spoiling: u32,
+    /// How it's been doing since it was put down
+    pub stats: TruckStats,
+    /// How far to move per tick.
+    pub speed: f32,
+    /// Where it's facing
+    pub facing: Direction,
+    /// The hex this truck is based on.
+    pub position: Coordinate,
+    /// The fraction this truck is to the next hexagon.
+    ///
+    ///-  `0.0` = directly on this one
+    /// - `1.0` = all the way on the other one
+    pub move_progress: f32,
+    /// If it's ok to be off the board right now
+    pub out_of_bounds_immunity: bool,
+    /// How many ticks it's been standing still for
+    pub waited: u32,
+    /// Ticks left until it comes out of a tunnel.
+    /// It's hidden and can't crash while it's down there.
+    pub underground: u32,
+    /// Where `get_xy` was before the last tick, for smoothing out drawing between ticks
+    #[serde(skip)]
+    pub last_xy: Option<(f32, f32)>,
+}
+
+impl Truck {
+    /// Get the hex coordinate this truck's center is over
+    pub fn get_hex(&self) -> Coordinate {
+        let target = self.position + self.facing;
+        if self.move_progress >= 0.5 {
+            target
+        } else {
+            self.position
+        }
+    }
+
+    /// Get the xy coordinates of the truck.
+    ///
+    /// - `(0, 0)` is the origin.
+    /// - `(1, 0)` is the center of the hex one to the right.
+    /// - `(0, 1)` is down one hex-radius from the center.
+    pub fn get_xy(&self) -> (f32, f32) {
+        self.get_xy_ahead(0.0)
+    }
+
+    /// Get the xy coordinates the truck would be at after going `distance` further the way it's facing.
+    pub fn get_xy_ahead(&self, distance: f32) -> (f32, f32) {
+        // +theta is clockwise in this weird world
+        let (dy, dx) = (self.facing.to_radians_pointy::<f32>() - TAU / 4.0).sin_cos();
+
+        let base = self
+            .position
+            .to_pixel(Spacing::PointyTop(3.0f32.sqrt().recip()));
+        let progress = self.move_progress + distance;
+        let x = base.0 + dx * progress;
+        let y = base.1 + dy * progress;
+        (x, y)
+    }
+
+    /// Get the xy coordinates of the truck `alpha` of the way from the last tick to now.
+    pub fn get_xy_between(&self, alpha: f32) -> (f32, f32) {
+        let (x, y) = self.get_xy();
+        match self.last_xy {
+            Some((lx, ly)) => (lx + (x - lx) * alpha, ly + (y - ly) * alpha),
+            None => (x, y),
+        }
+    }
+
+    /// Check if there's no room for anything else
+    pub fn is_full(&self) -> bool {
+        self.cargo.len() >= self.class.capacity()
+    }
+
+    /// How much money selling this truck back gets you
+    pub fn refund(&self) -> u32 {
+        let card = Card::Truck {
+            cargo: None,
+            class: self.class,
+        };
+        card.cost() * TRUCK_REFUND_PERCENT / 100
+    }
+
+    pub fn from_rot(
+        position: Coordinate,
+        class: TruckClass,
+        cargo: Option<Item>,
+        rotation: i32,
+    ) -> Self {
+        Truck {
+            id: TruckId::default(),
+            class,
+            cargo: cargo.into_iter().collect(),
+            spoiling: 0,
+            stats: TruckStats::default(),
+            facing: Direction::from_int(rotation),
+            move_progress: 0.0,
+            out_of_bounds_immunity: false,
+            waited: 0,
+            underground: 0,
+            position,
+            speed: class.speed(),
+            last_xy: None,
+        }
+    }
+}
+
+/// How well one truck has been doing, so the ones losing money can be found.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TruckStats {
+    /// How many items it's sold to markets
+    pub deliveries: u32,
+    /// Money it's made, less what it's cost in dump fees
+    pub revenue: i64,
+    /// How much it's put the tax up by
+    pub tax: u32,
+    /// How many hexes it's driven across
+    pub hexes: u32,
+    /// How many ticks it's been on the board
+    pub age: u64,
+}
+
+impl TruckStats {
+    /// Count up what this truck just did
+    pub fn record(&mut self, event: &Event) {
+        if let Event::Delivered { .. } = event {
+            self.deliveries += 1;
+        }
+        self.revenue += i64::from(event.money()) - i64::from(event.cost());
+        self.tax += event.tax();
+    }
+}
+
+/// The different kinds of truck you can put down.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TruckClass {
+    /// Small and quick
+    Van,
+    #[default]
+    Truck,
+    /// Big and slow
+    Lorry,
+    /// Keeps perishables from going off
+    Fridge,
+}
+
+impl TruckClass {
+    /// What this class is called
+    pub fn name(self) -> &'static str {
+        match self {
+            TruckClass::Van => "Van",
+            TruckClass::Truck => "Truck",
+            TruckClass::Lorry => "Lorry",
+            TruckClass::Fridge => "Fridge Truck",
+        }
+    }
+
+    /// How far a truck of this class moves per tick when it's first put down
+    pub fn speed(self) -> f32 {
+        match self {
+            TruckClass::Van => BASE_SPEED * 2.0,
+            TruckClass::Truck | TruckClass::Fridge => BASE_SPEED,
+            TruckClass::Lorry => BASE_SPEED / 2.0,
+        }
+    }
+
+    /// How many items a truck of this class can carry
+    pub fn capacity(self) -> usize {
+        match self {
+            TruckClass::Van => 1,
+            TruckClass::Truck | TruckClass::Fridge => 2,
+            TruckClass::Lorry => 4,
+        }
+    }
+
+    /// How close (in hexes) another truck has to get to this one to hit it.
+    /// Two trucks crash when they're closer than both their radii added up.
+    pub const fn crash_radius(self) -> f32 {
+        match self {
+            TruckClass::Van => 0.2,
+            TruckClass::Truck | TruckClass::Fridge => 0.25,
+            TruckClass::Lorry => 0.35,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Treads {
+    /// Board-pixel coordinates of the center of this tread
+    pub pos: (f32, f32),
+    /// Direction the truck was facing
+    pub facing: Direction,
+    /// Frames left alive
+    pub lifetime: u64,
+}